@@ -7,9 +7,12 @@ use strum_macros::{Display, EnumString};
 #[derive(Clone, Debug, Display, Hash, PartialEq, Eq, EnumString)]
 #[strum(serialize_all = "kebab-case")]
 pub enum LogSetting {
+    BlockDevices,
     Netstat,
     Platform,
+    Power,
     SerialPorts,
+    SystemdFailed,
     Cpu,
     Disk,
     Info,
@@ -37,10 +40,26 @@ pub struct Arguments {
     pub port: u16,
 
     /// Set logging intervals for various services in a comma-separated list (e.g., "system-cpu=10,system-disk=30")
-    /// Valid keys are: netstat, platform, serial-ports, system-cpu, system-disk, system-info, system-memory, system-network, system-process, system-temperature, system-unix-time-seconds
+    /// Valid keys are: block-devices, netstat, platform, power, serial-ports, systemd-failed, system-cpu, system-disk, system-info, system-memory, system-network, system-process, system-temperature, system-unix-time-seconds
     #[structopt(long, parse(try_from_str = parse_log_settings), default_value="")]
     pub log_settings: HashMap<LogSetting, u64>,
 
+    /// Allow changing cpufreq governor and frequency limits via PUT /system/cpufreq/{policy}
+    #[structopt(long)]
+    pub enable_cpufreq_control: bool,
+
+    /// Units allowed to be started, stopped or restarted via POST /systemd/units/{name}/{action} in a comma-separated list (e.g., "mavlink-router,video.service")
+    #[structopt(long, use_delimiter = true)]
+    pub systemd_control_units: Vec<String>,
+
+    /// Allow setting the system time via PUT /system/time for requests with an "Authorization: Bearer <token>" header, the token being read from this file
+    #[structopt(long, value_name = "PATH")]
+    pub time_control_token_file: Option<String>,
+
+    /// Sets the Docker or Podman API socket path, defaults to the first one available
+    #[structopt(long, value_name = "PATH")]
+    pub container_socket: Option<String>,
+
     /// Sets the zenoh configuration file path.
     #[structopt(long, value_name = "PATH")]
     pub zenoh_config_file: Option<String>,
@@ -98,8 +117,11 @@ fn validate_interval(key: &LogSetting, val: u64) -> Result<()> {
             "Interval for '{key:?}' must not be less than 5 seconds."
         )),
         LogSetting::Process
+        | LogSetting::BlockDevices
         | LogSetting::Netstat
+        | LogSetting::Power
         | LogSetting::SerialPorts
+        | LogSetting::SystemdFailed
         | LogSetting::Cpu
         | LogSetting::Memory
         | LogSetting::Network
@@ -0,0 +1,217 @@
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::*;
+
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+// /proc/diskstats always reports sectors of 512 bytes, independent of the device sector size
+const SECTOR_SIZE_B: u64 = 512;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct BlockDevice {
+    name: String,
+    major: u32,
+    minor: u32,
+    is_partition: bool,
+    model: Option<String>,
+    vendor: Option<String>,
+    size_B: u64,
+    is_rotational: bool,
+    is_removable: bool,
+    is_read_only: bool,
+
+    total_reads: u64,
+    total_read_B: u64,
+    total_writes: u64,
+    total_written_B: u64,
+
+    reads_per_second: f32,
+    read_B_per_second: f32,
+    read_await_ms: f32,
+    writes_per_second: f32,
+    written_B_per_second: f32,
+    write_await_ms: f32,
+    in_flight: u64,
+    utilization: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+struct DiskStats {
+    major: u32,
+    minor: u32,
+    name: String,
+    reads: u64,
+    sectors_read: u64,
+    read_time_ms: u64,
+    writes: u64,
+    sectors_written: u64,
+    write_time_ms: u64,
+    in_flight: u64,
+    io_time_ms: u64,
+}
+
+struct BlockDevicesService {
+    devices: Result<Vec<BlockDevice>, String>,
+    #[allow(dead_code)]
+    main_loop_thread: thread::JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref BLOCK_DEVICES_SERVICE: Arc<Mutex<BlockDevicesService>> =
+        Arc::new(Mutex::new(BlockDevicesService {
+            devices: Err("/proc/diskstats sampler not initialized".to_string()),
+            main_loop_thread: thread::spawn(run_main_loop),
+        }));
+}
+
+// Start sampling before the first request, rates need at least two samples to be available
+pub fn start() {
+    lazy_static::initialize(&BLOCK_DEVICES_SERVICE);
+}
+
+pub fn block_devices() -> Result<Vec<BlockDevice>, String> {
+    BLOCK_DEVICES_SERVICE.lock().unwrap().devices.clone()
+}
+
+fn read_diskstats() -> Result<Vec<DiskStats>, String> {
+    let content = fs::read_to_string("/proc/diskstats")
+        .map_err(|error| format!("Failed to read /proc/diskstats: {error}"))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let major = fields.next()?.parse().ok()?;
+            let minor = fields.next()?.parse().ok()?;
+            let name = fields.next()?.to_string();
+            let values = fields
+                .map(|value| value.parse::<u64>().unwrap_or_default())
+                .collect::<Vec<u64>>();
+            let value = |index: usize| values.get(index).copied().unwrap_or_default();
+
+            Some(DiskStats {
+                major,
+                minor,
+                name,
+                reads: value(0),
+                sectors_read: value(2),
+                read_time_ms: value(3),
+                writes: value(4),
+                sectors_written: value(6),
+                write_time_ms: value(7),
+                in_flight: value(8),
+                io_time_ms: value(9),
+            })
+        })
+        .collect())
+}
+
+impl BlockDevice {
+    fn from_samples(previous: Option<&DiskStats>, current: &DiskStats, interval: Duration) -> Self {
+        let sys_path = Path::new("/sys/class/block").join(&current.name);
+        let read = |file: &str| {
+            fs::read_to_string(sys_path.join(file))
+                .ok()
+                .map(|content| content.trim().to_string())
+                .filter(|content| !content.is_empty())
+        };
+        let read_flag = |file: &str| read(file).map(|value| value == "1").unwrap_or(false);
+
+        // Partitions do not have a queue or device, use the parent disk information instead
+        let is_partition = sys_path.join("partition").exists();
+        let parent = if is_partition { "../" } else { "" };
+
+        let previous = previous.cloned().unwrap_or_else(|| current.clone());
+        let delta = |previous: u64, current: u64| current.saturating_sub(previous);
+        let seconds = interval.as_secs_f32();
+        let rate = |value: u64| {
+            if seconds == 0.0 {
+                return 0.0;
+            }
+            value as f32 / seconds
+        };
+        let await_ms = |time_ms: u64, operations: u64| {
+            if operations == 0 {
+                return 0.0;
+            }
+            time_ms as f32 / operations as f32
+        };
+
+        let reads = delta(previous.reads, current.reads);
+        let writes = delta(previous.writes, current.writes);
+        let interval_ms = interval.as_millis() as f32;
+
+        BlockDevice {
+            name: current.name.clone(),
+            major: current.major,
+            minor: current.minor,
+            is_partition,
+            model: read(&format!("{parent}device/model")),
+            vendor: read(&format!("{parent}device/vendor")),
+            size_B: read("size")
+                .and_then(|size| size.parse::<u64>().ok())
+                .unwrap_or_default()
+                * SECTOR_SIZE_B,
+            is_rotational: read_flag(&format!("{parent}queue/rotational")),
+            is_removable: read_flag("removable"),
+            is_read_only: read_flag("ro"),
+
+            total_reads: current.reads,
+            total_read_B: current.sectors_read * SECTOR_SIZE_B,
+            total_writes: current.writes,
+            total_written_B: current.sectors_written * SECTOR_SIZE_B,
+
+            reads_per_second: rate(reads),
+            read_B_per_second: rate(
+                delta(previous.sectors_read, current.sectors_read) * SECTOR_SIZE_B,
+            ),
+            read_await_ms: await_ms(delta(previous.read_time_ms, current.read_time_ms), reads),
+            writes_per_second: rate(writes),
+            written_B_per_second: rate(
+                delta(previous.sectors_written, current.sectors_written) * SECTOR_SIZE_B,
+            ),
+            write_await_ms: await_ms(delta(previous.write_time_ms, current.write_time_ms), writes),
+            in_flight: current.in_flight,
+            utilization: if interval_ms == 0.0 {
+                0.0
+            } else {
+                (100.0 * delta(previous.io_time_ms, current.io_time_ms) as f32 / interval_ms)
+                    .min(100.0)
+            },
+        }
+    }
+}
+
+fn run_main_loop() {
+    let mut previous: Option<(Vec<DiskStats>, Instant)> = None;
+    loop {
+        let now = Instant::now();
+        match read_diskstats() {
+            Ok(stats) => {
+                if let Some((previous_stats, previous_time)) = &previous {
+                    let interval = now.duration_since(*previous_time);
+                    let devices = stats
+                        .iter()
+                        .map(|current| {
+                            let previous = previous_stats
+                                .iter()
+                                .find(|previous| previous.name == current.name);
+                            BlockDevice::from_samples(previous, current, interval)
+                        })
+                        .collect::<Vec<BlockDevice>>();
+                    BLOCK_DEVICES_SERVICE.lock().unwrap().devices = Ok(devices);
+                }
+                previous = Some((stats, now));
+            }
+            Err(error) => {
+                warn!("{error}");
+                BLOCK_DEVICES_SERVICE.lock().unwrap().devices = Err(error);
+            }
+        }
+        thread::sleep(SAMPLING_INTERVAL);
+    }
+}
@@ -0,0 +1,227 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Unified hierarchy, or its hybrid mode mount point when cgroup v1 controllers are also in use
+const CGROUP_PATHS: [&str; 2] = ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"];
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CgroupCpu {
+    usage_ms: u64,
+    user_ms: u64,
+    system_ms: u64,
+    periods: Option<u64>,
+    throttled_periods: Option<u64>,
+    throttled_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CgroupMemoryEvents {
+    low: u64,
+    high: u64,
+    max: u64,
+    oom: u64,
+    oom_kill: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CgroupMemory {
+    current_B: u64,
+    max_B: Option<u64>,
+    events: Option<CgroupMemoryEvents>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CgroupIo {
+    device: String,
+    read_B: u64,
+    written_B: u64,
+    reads: u64,
+    writes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Cgroup {
+    path: String,
+    name: String,
+    kind: String,
+    container_id: Option<String>,
+    cpu: Option<CgroupCpu>,
+    memory: Option<CgroupMemory>,
+    io: Vec<CgroupIo>,
+    pids_current: Option<u64>,
+    pids_max: Option<u64>,
+    children: Vec<Cgroup>,
+}
+
+fn read_string(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+fn read_number(path: &Path, file: &str) -> Option<u64> {
+    read_string(path, file).and_then(|content| content.parse().ok())
+}
+
+// Format: key value, one per line, e.g: usage_usec 1234
+fn read_flat_keyed(path: &Path, file: &str) -> Option<HashMap<String, u64>> {
+    let content = read_string(path, file)?;
+    Some(
+        content
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                Some((key.to_string(), value.trim().parse().ok()?))
+            })
+            .collect(),
+    )
+}
+
+// Format: major:minor key=value.., e.g: 8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0
+fn read_io(path: &Path) -> Vec<CgroupIo> {
+    read_string(path, "io.stat")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?.to_string();
+            let values = fields
+                .filter_map(|field| {
+                    let (key, value) = field.split_once('=')?;
+                    Some((key, value.parse::<u64>().ok()?))
+                })
+                .collect::<HashMap<&str, u64>>();
+            let value = |key: &str| values.get(key).copied().unwrap_or_default();
+            Some(CgroupIo {
+                device,
+                read_B: value("rbytes"),
+                written_B: value("wbytes"),
+                reads: value("rios"),
+                writes: value("wios"),
+            })
+        })
+        .collect()
+}
+
+// Container engines create scopes like docker-<id>.scope and libpod-<id>.scope, or cgroups like /docker/<id>
+fn container_id(path: &str, name: &str) -> Option<String> {
+    let id = name
+        .strip_suffix(".scope")
+        .and_then(|scope| {
+            scope
+                .strip_prefix("docker-")
+                .or_else(|| scope.strip_prefix("libpod-"))
+        })
+        .or_else(|| {
+            path.rsplit_once('/')
+                .filter(|(parent, _)| parent.ends_with("/docker") || parent.ends_with("/libpod"))
+                .map(|_| name)
+        })?;
+
+    if id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(id.to_string());
+    }
+    None
+}
+
+fn kind(path: &str, name: &str, container_id: &Option<String>) -> String {
+    if path == "/" {
+        return "root".into();
+    }
+    if container_id.is_some() {
+        return "container".into();
+    }
+    match name.rsplit_once('.') {
+        Some((_, suffix @ ("slice" | "service" | "scope" | "socket" | "mount" | "swap"))) => {
+            suffix.into()
+        }
+        _ => "cgroup".into(),
+    }
+}
+
+impl Cgroup {
+    fn from(root: &Path, path: &Path) -> Self {
+        let relative_path = format!(
+            "/{}",
+            path.strip_prefix(root).unwrap_or(path).to_string_lossy()
+        );
+        let name = path
+            .file_name()
+            .filter(|_| relative_path != "/")
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".into());
+        let container_id = container_id(&relative_path, &name);
+
+        // usage_usec user_usec system_usec, throttling is only available with the cpu controller
+        let cpu = read_flat_keyed(path, "cpu.stat").map(|stat| {
+            let value = |key: &str| stat.get(key).copied();
+            CgroupCpu {
+                usage_ms: value("usage_usec").unwrap_or_default() / 1000,
+                user_ms: value("user_usec").unwrap_or_default() / 1000,
+                system_ms: value("system_usec").unwrap_or_default() / 1000,
+                periods: value("nr_periods"),
+                throttled_periods: value("nr_throttled"),
+                throttled_ms: value("throttled_usec").map(|throttled| throttled / 1000),
+            }
+        });
+
+        let memory = read_number(path, "memory.current").map(|current_B| CgroupMemory {
+            current_B,
+            // Limits are "max" when unlimited
+            max_B: read_number(path, "memory.max"),
+            events: read_flat_keyed(path, "memory.events").map(|events| {
+                let value = |key: &str| events.get(key).copied().unwrap_or_default();
+                CgroupMemoryEvents {
+                    low: value("low"),
+                    high: value("high"),
+                    max: value("max"),
+                    oom: value("oom"),
+                    oom_kill: value("oom_kill"),
+                }
+            }),
+        });
+
+        let mut children = fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().map_or(false, |kind| kind.is_dir()))
+                    .map(|entry| entry.path())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        children.sort();
+
+        Cgroup {
+            kind: kind(&relative_path, &name, &container_id),
+            path: relative_path,
+            name,
+            container_id,
+            cpu,
+            memory,
+            io: read_io(path),
+            pids_current: read_number(path, "pids.current"),
+            pids_max: read_number(path, "pids.max"),
+            children: children
+                .iter()
+                .map(|child| Cgroup::from(root, child))
+                .collect(),
+        }
+    }
+}
+
+// Provides the cgroup tree, starting from the root cgroup
+#[cached(time = 5)]
+pub fn cgroups() -> Result<Cgroup, String> {
+    let root = CGROUP_PATHS
+        .iter()
+        .map(Path::new)
+        .find(|path| path.join("cgroup.controllers").exists())
+        .ok_or_else(|| "cgroup v2 hierarchy is not mounted".to_string())?;
+
+    Ok(Cgroup::from(root, root))
+}
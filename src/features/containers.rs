@@ -0,0 +1,525 @@
+use cached::proc_macro::cached;
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use futures::SinkExt;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tracing::*;
+
+// Docker socket first, then Podman's Docker compatible API sockets
+const SOCKET_PATHS: [&str; 3] = [
+    "/var/run/docker.sock",
+    "/run/podman/podman.sock",
+    "/var/run/podman/podman.sock",
+];
+// A stats request takes up to two seconds, anything longer means the engine is not answering
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const LOGS_TAIL_LINES: usize = 100;
+const LOGS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Engines on small boards struggle with many concurrent stats requests
+const MAX_PARALLEL_REQUESTS: usize = 4;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ContainerPort {
+    ip: Option<String>,
+    private_port: u16,
+    public_port: Option<u16>,
+    protocol: String,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ContainerStats {
+    cpu_usage: f32,
+    memory_usage_B: u64,
+    memory_limit_B: u64,
+    network_received_B: u64,
+    network_transmitted_B: u64,
+    pids: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Container {
+    id: String,
+    name: String,
+    image: String,
+    command: String,
+    created: i64,
+    state: String,
+    status: String,
+    restart_count: Option<u64>,
+    health: Option<String>,
+    ports: Vec<ContainerPort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<ContainerStats>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ContainerLogLine {
+    stream: String,
+    timestamp: Option<String>,
+    message: String,
+}
+
+fn socket_path() -> Result<String, String> {
+    if let Some(path) = &crate::cli::args().container_socket {
+        return Ok(path.clone());
+    }
+
+    SOCKET_PATHS
+        .iter()
+        .find(|path| Path::new(path).exists())
+        .map(|path| path.to_string())
+        .ok_or_else(|| {
+            format!(
+                "No container engine socket found in: {}",
+                SOCKET_PATHS.join(", ")
+            )
+        })
+}
+
+// Sends a HTTP/1.0 request so the engine closes the connection instead of using chunked encoding
+fn request(socket_path: &str, path: &str) -> Result<BufReader<UnixStream>, String> {
+    let mut stream = UnixStream::connect(socket_path)
+        .map_err(|error| format!("Failed to connect to {socket_path}: {error}"))?;
+    stream
+        .set_read_timeout(Some(REQUEST_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(REQUEST_TIMEOUT)))
+        .map_err(|error| format!("Failed to configure {socket_path} connection: {error}"))?;
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n").as_bytes())
+        .map_err(|error| format!("Failed to send request to {socket_path}: {error}"))?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader
+        .read_line(&mut status_line)
+        .map_err(|error| format!("Failed to read response from {socket_path}: {error}"))?;
+    // e.g: HTTP/1.0 200 OK
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| format!("Invalid response from {socket_path}: {status_line}"))?;
+
+    // Skip headers until the empty line before the body
+    loop {
+        let mut line = String::new();
+        let size = reader
+            .read_line(&mut line)
+            .map_err(|error| format!("Failed to read response from {socket_path}: {error}"))?;
+        if size == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    if !(200..300).contains(&status) {
+        let mut body = String::new();
+        let _ = reader.read_to_string(&mut body);
+        // Errors are provided as {"message": "..."}
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| value.get("message")?.as_str().map(String::from))
+            .unwrap_or(body);
+        return Err(format!(
+            "GET {path} failed with {status}: {}",
+            message.trim()
+        ));
+    }
+
+    Ok(reader)
+}
+
+fn get(socket_path: &str, path: &str) -> Result<Value, String> {
+    let mut body = String::new();
+    request(socket_path, path)?
+        .read_to_string(&mut body)
+        .map_err(|error| format!("Failed to read {path} response: {error}"))?;
+    serde_json::from_str(&body).map_err(|error| format!("Invalid {path} json payload: {error}"))
+}
+
+fn container_stats(socket_path: &str, id: &str) -> Result<ContainerStats, String> {
+    // Without streaming the engine waits for a second sample to fill precpu_stats
+    let stats = get(socket_path, &format!("/containers/{id}/stats?stream=false"))?;
+    let number = |pointer: &str| {
+        stats
+            .pointer(pointer)
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default()
+    };
+
+    let cpu_delta = number("/cpu_stats/cpu_usage/total_usage")
+        .saturating_sub(number("/precpu_stats/cpu_usage/total_usage"));
+    let system_delta = number("/cpu_stats/system_cpu_usage")
+        .saturating_sub(number("/precpu_stats/system_cpu_usage"));
+    let online_cpus = number("/cpu_stats/online_cpus").max(1);
+    let cpu_usage = if system_delta == 0 {
+        0.0
+    } else {
+        100.0 * cpu_delta as f32 / system_delta as f32 * online_cpus as f32
+    };
+
+    // Page cache can be reclaimed, docker stats removes it from the usage as well
+    let cache = stats
+        .pointer("/memory_stats/stats/inactive_file")
+        .or_else(|| stats.pointer("/memory_stats/stats/total_inactive_file"))
+        .or_else(|| stats.pointer("/memory_stats/stats/cache"))
+        .and_then(|value| value.as_u64())
+        .unwrap_or_default();
+
+    let (network_received_B, network_transmitted_B) = stats
+        .get("networks")
+        .and_then(|networks| networks.as_object())
+        .map(|networks| {
+            networks
+                .values()
+                .fold((0, 0), |(received, transmitted), network| {
+                    let value = |name: &str| network.get(name).and_then(|value| value.as_u64());
+                    (
+                        received + value("rx_bytes").unwrap_or_default(),
+                        transmitted + value("tx_bytes").unwrap_or_default(),
+                    )
+                })
+        })
+        .unwrap_or_default();
+
+    Ok(ContainerStats {
+        cpu_usage,
+        memory_usage_B: number("/memory_stats/usage").saturating_sub(cache),
+        memory_limit_B: number("/memory_stats/limit"),
+        network_received_B,
+        network_transmitted_B,
+        pids: stats
+            .pointer("/pids_stats/current")
+            .and_then(|value| value.as_u64()),
+    })
+}
+
+impl Container {
+    fn from(summary: &Value) -> Option<Self> {
+        let string = |name: &str| {
+            summary
+                .get(name)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let ports = summary
+            .get("Ports")
+            .and_then(|ports| ports.as_array())
+            .map(|ports| {
+                ports
+                    .iter()
+                    .filter_map(|port| {
+                        Some(ContainerPort {
+                            ip: port.get("IP").and_then(|ip| ip.as_str()).map(String::from),
+                            private_port: port.get("PrivatePort")?.as_u64()? as u16,
+                            public_port: port
+                                .get("PublicPort")
+                                .and_then(|port| port.as_u64())
+                                .map(|port| port as u16),
+                            protocol: port.get("Type")?.as_str()?.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Container {
+            id: summary.get("Id")?.as_str()?.to_string(),
+            name: summary
+                .get("Names")
+                .and_then(|names| names.get(0))
+                .and_then(|name| name.as_str())
+                .map(|name| name.trim_start_matches('/').to_string())
+                .unwrap_or_default(),
+            image: string("Image"),
+            command: string("Command"),
+            created: summary
+                .get("Created")
+                .and_then(|created| created.as_i64())
+                .unwrap_or_default(),
+            state: string("State"),
+            status: string("Status"),
+            restart_count: None,
+            health: None,
+            ports,
+            stats: None,
+        })
+    }
+
+    // Restart count and health are only available when inspecting the container
+    fn inspect(&mut self, socket_path: &str) -> Result<(), String> {
+        let inspect = get(socket_path, &format!("/containers/{}/json", self.id))?;
+        self.restart_count = inspect.get("RestartCount").and_then(|count| count.as_u64());
+        self.health = inspect
+            .pointer("/State/Health/Status")
+            .and_then(|health| health.as_str())
+            .map(String::from);
+        Ok(())
+    }
+}
+
+#[cached(time = 5)]
+pub fn containers(stats: Option<bool>) -> Result<Vec<Container>, String> {
+    fetch_containers(&socket_path()?, stats.unwrap_or(false))
+}
+
+fn fetch_containers(socket_path: &str, stats: bool) -> Result<Vec<Container>, String> {
+    let summaries = get(socket_path, "/containers/json?all=true")?;
+    let mut containers = summaries
+        .as_array()
+        .ok_or_else(|| "Expected a list of containers".to_string())?
+        .iter()
+        .filter_map(Container::from)
+        .collect::<Vec<Container>>();
+
+    // Each stats request takes about a second, a few workers share the list of containers
+    let queue = Mutex::new(containers.iter_mut());
+    thread::scope(|scope| {
+        for _ in 0..MAX_PARALLEL_REQUESTS {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let Some(container) = next else {
+                    break;
+                };
+                if let Err(error) = container.inspect(socket_path) {
+                    warn!("{error}");
+                }
+                if stats && container.state == "running" {
+                    match container_stats(socket_path, &container.id) {
+                        Ok(stats) => container.stats = Some(stats),
+                        Err(error) => warn!("{error}"),
+                    }
+                }
+            });
+        }
+    });
+
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(containers)
+}
+
+fn send(sender: &mut Sender<String>, line: ContainerLogLine) -> bool {
+    let Ok(serialized) = serde_json::to_string(&line) else {
+        return true;
+    };
+    futures::executor::block_on(sender.send(serialized)).is_ok()
+}
+
+// Splits the RFC3339 timestamp added by the engine from the message
+fn log_line(stream: &str, content: &[u8]) -> ContainerLogLine {
+    let content = String::from_utf8_lossy(content);
+    let content = content.trim_end_matches(['\r', '\n']);
+    let (timestamp, message) = match content.split_once(' ') {
+        Some((timestamp, message)) if timestamp.contains('T') => (Some(timestamp), message),
+        _ => (None, content),
+    };
+    ContainerLogLine {
+        stream: stream.into(),
+        timestamp: timestamp.map(String::from),
+        message: message.into(),
+    }
+}
+
+fn stream_logs(id: &str, mut sender: Sender<String>) -> Result<(), String> {
+    // Ids and names are used in the request line, do not allow anything else
+    let is_valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !is_valid {
+        return Err(format!("Invalid container id: {id}"));
+    }
+
+    let socket_path = socket_path()?;
+    let is_tty = get(&socket_path, &format!("/containers/{id}/json"))?
+        .pointer("/Config/Tty")
+        .and_then(|tty| tty.as_bool())
+        .unwrap_or(false);
+
+    let mut reader = request(
+        &socket_path,
+        &format!(
+        "/containers/{id}/logs?follow=true&stdout=true&stderr=true&timestamps=true&tail={LOGS_TAIL_LINES}"
+        ),
+    )?;
+    // Wake up periodically to stop once the websocket client is gone
+    reader
+        .get_ref()
+        .set_read_timeout(Some(LOGS_POLL_INTERVAL))
+        .map_err(|error| format!("Failed to configure logs stream: {error}"))?;
+
+    let mut pending: Vec<u8> = vec![];
+    let mut buffer = [0u8; 8192];
+    loop {
+        if sender.is_closed() {
+            return Ok(());
+        }
+
+        let size = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(size) => size,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(error) => return Err(format!("Failed to read container logs: {error}")),
+        };
+        pending.extend_from_slice(&buffer[..size]);
+
+        // TTY containers provide raw output, others multiplex stdout and stderr in frames:
+        // [stream type, 0, 0, 0, size (u32 big endian)] followed by the payload
+        loop {
+            let line = if is_tty {
+                let Some(end) = pending.iter().position(|byte| *byte == b'\n') else {
+                    break;
+                };
+                let line = log_line("stdout", &pending[..end]);
+                pending.drain(..=end);
+                line
+            } else {
+                if pending.len() < 8 {
+                    break;
+                }
+                let size =
+                    u32::from_be_bytes([pending[4], pending[5], pending[6], pending[7]]) as usize;
+                if pending.len() < 8 + size {
+                    break;
+                }
+                let stream = if pending[0] == 2 { "stderr" } else { "stdout" };
+                let line = log_line(stream, &pending[8..8 + size]);
+                pending.drain(..8 + size);
+                line
+            };
+
+            if !send(&mut sender, line) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Follows the container logs, starting with the last lines, until the receiver is dropped
+pub fn ask_for_logs(id: String) -> Receiver<String> {
+    let (mut sender, receiver) = channel(1024);
+
+    thread::spawn(move || {
+        if let Err(error) = stream_logs(&id, sender.clone()) {
+            warn!("{error}");
+            let _ = futures::executor::block_on(
+                sender.send(format!("{{\"error\":{}}}", Value::String(error))),
+            );
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    // Serves canned Docker API responses, one request per connection like HTTP/1.0 clients
+    fn serve(name: &str) -> String {
+        let socket_path = std::env::temp_dir()
+            .join(format!("linux2rest-{name}-{}.sock", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+                let (status, body) = match path {
+                    "/containers/json?all=true" => (
+                        "200 OK",
+                        r#"[
+                            {"Id":"b2","Names":["/web"],"Image":"nginx","Command":"nginx","Created":1700000000,"State":"running","Status":"Up 2 hours","Ports":[{"IP":"0.0.0.0","PrivatePort":80,"PublicPort":8080,"Type":"tcp"}]},
+                            {"Id":"a1","Names":["/backup"],"Image":"alpine","Command":"sh","Created":1600000000,"State":"exited","Status":"Exited (0)","Ports":[]}
+                        ]"#,
+                    ),
+                    "/containers/b2/json" => (
+                        "200 OK",
+                        r#"{"RestartCount":3,"State":{"Health":{"Status":"healthy"}}}"#,
+                    ),
+                    "/containers/a1/json" => ("200 OK", r#"{"RestartCount":0,"State":{}}"#),
+                    "/containers/b2/stats?stream=false" => (
+                        "200 OK",
+                        r#"{
+                            "cpu_stats":{"cpu_usage":{"total_usage":3000},"system_cpu_usage":20000,"online_cpus":2},
+                            "precpu_stats":{"cpu_usage":{"total_usage":1000},"system_cpu_usage":10000},
+                            "memory_stats":{"usage":5000,"limit":100000,"stats":{"inactive_file":1000}},
+                            "networks":{"eth0":{"rx_bytes":10,"tx_bytes":20},"eth1":{"rx_bytes":1,"tx_bytes":2}},
+                            "pids_stats":{"current":7}
+                        }"#,
+                    ),
+                    _ => ("404 Not Found", r#"{"message":"No such container"}"#),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.0 {status}\r\nContent-Type: application/json\r\n\r\n{body}"
+                );
+            }
+        });
+
+        socket_path
+    }
+
+    #[test]
+    fn lists_containers_with_stats() {
+        let socket_path = serve("containers");
+        let containers = fetch_containers(&socket_path, true).unwrap();
+
+        assert_eq!(containers.len(), 2);
+        let backup = &containers[0];
+        assert_eq!(backup.name, "backup");
+        assert_eq!(backup.restart_count, Some(0));
+        assert_eq!(backup.health, None);
+        assert!(backup.stats.is_none());
+
+        let web = &containers[1];
+        assert_eq!(web.name, "web");
+        assert_eq!(web.restart_count, Some(3));
+        assert_eq!(web.health.as_deref(), Some("healthy"));
+        assert_eq!(web.ports.len(), 1);
+        assert_eq!(web.ports[0].public_port, Some(8080));
+
+        let stats = web.stats.as_ref().unwrap();
+        assert_eq!(stats.cpu_usage, 40.0);
+        assert_eq!(stats.memory_usage_B, 4000);
+        assert_eq!(stats.memory_limit_B, 100000);
+        assert_eq!(stats.network_received_B, 11);
+        assert_eq!(stats.network_transmitted_B, 22);
+        assert_eq!(stats.pids, Some(7));
+
+        let containers = fetch_containers(&socket_path, false).unwrap();
+        assert!(containers.iter().all(|container| container.stats.is_none()));
+    }
+
+    #[test]
+    fn reports_engine_errors() {
+        let socket_path = serve("errors");
+        assert_eq!(
+            get(&socket_path, "/containers/missing/json").unwrap_err(),
+            "GET /containers/missing/json failed with 404: No such container"
+        );
+    }
+}
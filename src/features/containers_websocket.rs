@@ -0,0 +1,57 @@
+use crate::features::containers;
+use actix::{self, Actor, ActorContext, StreamHandler};
+use actix_web_actors::ws;
+use futures::channel::mpsc::Receiver;
+use tracing::*;
+
+pub fn new_websocket(id: String) -> WebsocketActor {
+    WebsocketActor::new(id)
+}
+
+pub struct WebsocketActor {
+    id: String,
+    receiver: Option<Receiver<String>>,
+}
+
+impl WebsocketActor {
+    pub fn new(id: String) -> Self {
+        Self {
+            receiver: Some(containers::ask_for_logs(id.clone())),
+            id,
+        }
+    }
+}
+
+impl Actor for WebsocketActor {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("Starting container {} logs websocket", self.id);
+        ctx.add_stream(self.receiver.take().unwrap());
+    }
+}
+
+impl StreamHandler<String> for WebsocketActor {
+    fn handle(&mut self, data: String, ctx: &mut Self::Context) {
+        ctx.text(data)
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketActor {
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        // Stopping the actor drops the receiver, which stops the logs follower thread
+        debug!("Finishing container {} logs websocket", self.id);
+        ctx.stop();
+    }
+
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Text(_)) => {
+                ctx.text("{\"error\":\"Websocket does not support inputs.\"}");
+            }
+            Ok(ws::Message::Binary(bin)) => ctx.binary(bin),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::*;
+
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpufreq";
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct TimeInState {
+    frequency_kHz: u64,
+    time_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CpuFreqPolicy {
+    name: String,
+    cpus: Vec<u32>,
+    driver: String,
+    governor: String,
+    available_governors: Vec<String>,
+    current_frequency_kHz: Option<u64>,
+    min_frequency_kHz: Option<u64>,
+    max_frequency_kHz: Option<u64>,
+    hardware_min_frequency_kHz: Option<u64>,
+    hardware_max_frequency_kHz: Option<u64>,
+    available_frequencies_kHz: Vec<u64>,
+    time_in_state: Vec<TimeInState>,
+}
+
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct CpuFreqPolicyUpdate {
+    governor: Option<String>,
+    min_frequency_kHz: Option<u64>,
+    max_frequency_kHz: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum CpuFreqError {
+    NotFound(String),
+    Invalid(String),
+    Io(String),
+}
+
+impl std::fmt::Display for CpuFreqError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuFreqError::NotFound(message)
+            | CpuFreqError::Invalid(message)
+            | CpuFreqError::Io(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+fn read_string(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+fn read_number(path: &Path, file: &str) -> Option<u64> {
+    read_string(path, file).and_then(|content| content.parse().ok())
+}
+
+fn read_list<T: std::str::FromStr>(path: &Path, file: &str) -> Vec<T> {
+    read_string(path, file)
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect()
+}
+
+impl CpuFreqPolicy {
+    fn from(path: &Path) -> Self {
+        let time_in_state = read_string(path, "stats/time_in_state")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut values = line.split_whitespace();
+                let frequency_kHz = values.next()?.parse::<u64>().ok()?;
+                // Time is reported in USER_HZ units, 10ms
+                let time_ms = values.next()?.parse::<u64>().ok()? * 10;
+                Some(TimeInState {
+                    frequency_kHz,
+                    time_ms,
+                })
+            })
+            .collect();
+
+        CpuFreqPolicy {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            cpus: read_list(path, "affected_cpus"),
+            driver: read_string(path, "scaling_driver").unwrap_or_default(),
+            governor: read_string(path, "scaling_governor").unwrap_or_default(),
+            available_governors: read_list(path, "scaling_available_governors"),
+            current_frequency_kHz: read_number(path, "scaling_cur_freq"),
+            min_frequency_kHz: read_number(path, "scaling_min_freq"),
+            max_frequency_kHz: read_number(path, "scaling_max_freq"),
+            hardware_min_frequency_kHz: read_number(path, "cpuinfo_min_freq"),
+            hardware_max_frequency_kHz: read_number(path, "cpuinfo_max_freq"),
+            available_frequencies_kHz: read_list(path, "scaling_available_frequencies"),
+            time_in_state,
+        }
+    }
+}
+
+#[cached(time = 5)]
+pub fn policies() -> Vec<CpuFreqPolicy> {
+    let entries = match fs::read_dir(CPUFREQ_PATH) {
+        Ok(entries) => entries,
+        Err(error) => {
+            debug!("Failed to look over {CPUFREQ_PATH}: {error}");
+            return vec![];
+        }
+    };
+
+    let mut policies = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().starts_with("policy"))
+                .unwrap_or(false)
+        })
+        .map(|path| CpuFreqPolicy::from(&path))
+        .collect::<Vec<CpuFreqPolicy>>();
+
+    policies.sort_by_key(|policy| {
+        policy
+            .name
+            .trim_start_matches("policy")
+            .parse::<u32>()
+            .unwrap_or_default()
+    });
+    policies
+}
+
+fn policy_path(name: &str) -> Result<PathBuf, CpuFreqError> {
+    // Only accept policyN to avoid writing anywhere else in sysfs
+    let is_valid = name
+        .strip_prefix("policy")
+        .map(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false);
+    let path = Path::new(CPUFREQ_PATH).join(name);
+    if !is_valid || !path.exists() {
+        return Err(CpuFreqError::NotFound(format!(
+            "cpufreq policy not found: {name}"
+        )));
+    }
+    Ok(path)
+}
+
+fn write(path: &Path, file: &str, value: &str) -> Result<(), CpuFreqError> {
+    fs::write(path.join(file), value).map_err(|error| {
+        CpuFreqError::Io(format!(
+            "Failed to write {value} to {:?}: {error}",
+            path.join(file)
+        ))
+    })
+}
+
+pub fn update_policy(
+    name: &str,
+    update: &CpuFreqPolicyUpdate,
+) -> Result<CpuFreqPolicy, CpuFreqError> {
+    let path = policy_path(name)?;
+    let policy = CpuFreqPolicy::from(&path);
+
+    if let Some(governor) = &update.governor {
+        if !policy.available_governors.contains(governor) {
+            return Err(CpuFreqError::Invalid(format!(
+                "Invalid governor {governor}, available: {:?}",
+                policy.available_governors
+            )));
+        }
+    }
+
+    let is_updating_limits =
+        update.min_frequency_kHz.is_some() || update.max_frequency_kHz.is_some();
+    let (hardware_min, hardware_max) = match (
+        policy.hardware_min_frequency_kHz,
+        policy.hardware_max_frequency_kHz,
+    ) {
+        (Some(min), Some(max)) => (min, max),
+        _ if is_updating_limits => {
+            return Err(CpuFreqError::Invalid(format!(
+                "Hardware frequency limits of {name} are unknown, refusing to change them"
+            )))
+        }
+        _ => (0, u64::MAX),
+    };
+    for frequency in [update.min_frequency_kHz, update.max_frequency_kHz]
+        .iter()
+        .flatten()
+    {
+        if *frequency < hardware_min || *frequency > hardware_max {
+            return Err(CpuFreqError::Invalid(format!(
+                "Frequency {frequency} kHz is outside of the hardware limits: {hardware_min}..{hardware_max} kHz"
+            )));
+        }
+    }
+
+    let min = update
+        .min_frequency_kHz
+        .or(policy.min_frequency_kHz)
+        .unwrap_or(hardware_min);
+    let max = update
+        .max_frequency_kHz
+        .or(policy.max_frequency_kHz)
+        .unwrap_or(hardware_max);
+    if min > max {
+        return Err(CpuFreqError::Invalid(format!(
+            "Minimum frequency {min} kHz is higher than maximum frequency {max} kHz"
+        )));
+    }
+
+    // The kernel refuses a minimum above the current maximum and vice versa, so order matters
+    let raising_min_above_max = update
+        .min_frequency_kHz
+        .zip(policy.max_frequency_kHz)
+        .map(|(min, current_max)| min > current_max)
+        .unwrap_or(false);
+    let mut writes = vec![
+        (
+            "scaling_min_freq",
+            update
+                .min_frequency_kHz
+                .map(|frequency| frequency.to_string()),
+            policy
+                .min_frequency_kHz
+                .map(|frequency| frequency.to_string()),
+        ),
+        (
+            "scaling_max_freq",
+            update
+                .max_frequency_kHz
+                .map(|frequency| frequency.to_string()),
+            policy
+                .max_frequency_kHz
+                .map(|frequency| frequency.to_string()),
+        ),
+    ];
+    if raising_min_above_max {
+        writes.reverse();
+    }
+    // Governor goes last, limits are the writes most likely to be refused
+    writes.push((
+        "scaling_governor",
+        update.governor.clone(),
+        Some(policy.governor.clone()),
+    ));
+
+    let result = apply(&path, &writes);
+    clear_cache();
+    result?;
+
+    Ok(CpuFreqPolicy::from(&path))
+}
+
+// Applies all writes or none, restoring the previous values when one of them is refused
+fn apply(
+    path: &Path,
+    writes: &[(&str, Option<String>, Option<String>)],
+) -> Result<(), CpuFreqError> {
+    let mut applied = vec![];
+    for (file, value, previous) in writes {
+        let Some(value) = value else {
+            continue;
+        };
+        if let Err(error) = write(path, file, value) {
+            for (file, previous) in applied.into_iter().rev() {
+                if let Err(error) = write(path, file, previous) {
+                    warn!("Failed to restore cpufreq {path:?} {file}: {error}");
+                }
+            }
+            return Err(error);
+        }
+        info!("Changed cpufreq {path:?} {file} to {value}");
+        if let Some(previous) = previous {
+            applied.push((*file, previous));
+        }
+    }
+    Ok(())
+}
+
+fn clear_cache() {
+    use cached::Cached;
+    POLICIES.lock().unwrap().cache_clear();
+}
@@ -0,0 +1,15 @@
+use crate::features::time;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Health {
+    // Timestamps are unreliable until the clock is synchronized, e.g: boards without RTC
+    is_clock_synchronized: bool,
+}
+
+pub fn health() -> Health {
+    Health {
+        is_clock_synchronized: time::is_synchronized(),
+    }
+}
@@ -13,7 +13,7 @@ use tracing::*;
 
 const MAX_ENTRIES: usize = 200000;
 
-#[derive(Clone, Serialize, Apiv2Schema)]
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
 pub struct JournalEntry {
     cursor: String,
     realtime_timestamp: u64,
@@ -27,6 +27,9 @@ pub struct JournalEntry {
     pid: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     unit: Option<String>,
+    // Unit the message is about, e.g: "Failed to start X" logged by systemd for X
+    #[serde(skip_serializing_if = "Option::is_none")]
+    object_unit: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     hostname: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -92,6 +95,23 @@ pub fn entries(start: Option<usize>, size: Option<usize>) -> JournalResponse {
     }
 }
 
+// Provides the last entries logged by or about a systemd unit, oldest first
+pub fn unit_entries(unit: &str, size: usize) -> Vec<JournalEntry> {
+    let journal_service = JOURNAL_SERVICE.lock().unwrap();
+    let mut entries = journal_service
+        .entries
+        .iter()
+        .rev()
+        .filter(|entry| {
+            entry.unit.as_deref() == Some(unit) || entry.object_unit.as_deref() == Some(unit)
+        })
+        .take(size)
+        .cloned()
+        .collect::<Vec<_>>();
+    entries.reverse();
+    entries
+}
+
 fn run_main_loop() {
     loop {
         match stream_journal() {
@@ -189,6 +209,15 @@ fn parse_entry(line: &str) -> Result<JournalEntry, String> {
         .and_then(|v| v.as_str())
         .map(|v| v.to_string());
 
+    // Set by systemd itself (UNIT, USER_UNIT) or by privileged processes logging about a unit
+    let object_unit = value
+        .get("UNIT")
+        .or_else(|| value.get("USER_UNIT"))
+        .or_else(|| value.get("OBJECT_SYSTEMD_UNIT"))
+        .or_else(|| value.get("OBJECT_SYSTEMD_USER_UNIT"))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string());
+
     let hostname = value
         .get("_HOSTNAME")
         .and_then(|v| v.as_str())
@@ -208,6 +237,7 @@ fn parse_entry(line: &str) -> Result<JournalEntry, String> {
         identifier,
         pid,
         unit,
+        object_unit,
         hostname,
         boot_id,
     })
@@ -274,7 +304,7 @@ fn clear_error() {
     journal_service.error = None;
 }
 
-fn format_timestamp(microseconds: u64) -> String {
+pub fn format_timestamp(microseconds: u64) -> String {
     let seconds = (microseconds / 1_000_000) as i64;
     let nanos = ((microseconds % 1_000_000) * 1_000) as u32;
     match NaiveDateTime::from_timestamp_opt(seconds, nanos) {
@@ -282,3 +312,23 @@ fn format_timestamp(microseconds: u64) -> String {
         None => "unknown".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unit_the_message_is_about() {
+        let line = r#"{"__CURSOR":"s=1;i=2","__REALTIME_TIMESTAMP":"1700000000000000","MESSAGE":"Failed to start Video.","PRIORITY":"3","SYSLOG_IDENTIFIER":"systemd","_PID":"1","_SYSTEMD_UNIT":"init.scope","UNIT":"video.service"}"#;
+
+        let entry = parse_entry(line).unwrap();
+        assert_eq!(entry.unit.as_deref(), Some("init.scope"));
+        assert_eq!(entry.object_unit.as_deref(), Some("video.service"));
+        assert_eq!(entry.timestamp, "2023-11-14T22:13:20+00:00");
+
+        let line = r#"{"__CURSOR":"s=1;i=3","MESSAGE":"ready","_SYSTEMD_UNIT":"video.service"}"#;
+        let entry = parse_entry(line).unwrap();
+        assert_eq!(entry.unit.as_deref(), Some("video.service"));
+        assert_eq!(entry.object_unit, None);
+    }
+}
@@ -0,0 +1,218 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+const SYSCTL_PATH: &str = "/proc/sys";
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct KernelModule {
+    name: String,
+    size_B: u64,
+    reference_count: i64,
+    dependents: Vec<String>,
+    state: String,
+    version: Option<String>,
+    taint: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct KernelParameter {
+    key: String,
+    value: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct KernelCmdline {
+    cmdline: String,
+    parameters: Vec<KernelParameter>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Sysctl {
+    key: String,
+    value: String,
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+// Format: name size refcount dependents state offset [taint], e.g:
+// nf_tables 245760 123 nft_ct,nft_chain_nat, Live 0x0000000000000000
+// Dependents are "-" when there are none, and refcount is "-" when unloading is not supported
+fn parse_module(line: &str) -> Option<KernelModule> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?.to_string();
+    let size_B = fields.next()?.parse().ok()?;
+    let reference_count = fields.next()?.parse().unwrap_or(-1);
+    let dependents = fields
+        .next()?
+        .split(',')
+        .filter(|dependent| !dependent.is_empty() && *dependent != "-")
+        .map(String::from)
+        .collect();
+    let state = fields.next()?.to_lowercase();
+
+    let module_path = Path::new("/sys/module").join(&name);
+    Some(KernelModule {
+        version: read_string(&module_path.join("version")),
+        taint: read_string(&module_path.join("taint")),
+        name,
+        size_B,
+        reference_count,
+        dependents,
+        state,
+    })
+}
+
+#[cached(time = 5)]
+pub fn modules() -> Result<Vec<KernelModule>, String> {
+    let content = fs::read_to_string("/proc/modules")
+        .map_err(|error| format!("Failed to read /proc/modules: {error}"))?;
+
+    let mut modules = content
+        .lines()
+        .filter_map(parse_module)
+        .collect::<Vec<KernelModule>>();
+    modules.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(modules)
+}
+
+// Splits parameters by spaces, except inside double quotes, e.g: dyndbg="file drm* +p" quiet
+fn split_cmdline(cmdline: &str) -> Vec<String> {
+    let mut parameters = vec![];
+    let mut parameter = String::new();
+    let mut is_quoted = false;
+    for c in cmdline.chars() {
+        match c {
+            '"' => is_quoted = !is_quoted,
+            c if c.is_whitespace() && !is_quoted => {
+                if !parameter.is_empty() {
+                    parameters.push(std::mem::take(&mut parameter));
+                }
+            }
+            c => parameter.push(c),
+        }
+    }
+    if !parameter.is_empty() {
+        parameters.push(parameter);
+    }
+    parameters
+}
+
+pub fn cmdline() -> Result<KernelCmdline, String> {
+    let cmdline = fs::read_to_string("/proc/cmdline")
+        .map_err(|error| format!("Failed to read /proc/cmdline: {error}"))?
+        .trim()
+        .to_string();
+
+    let parameters = split_cmdline(&cmdline)
+        .into_iter()
+        .map(|parameter| match parameter.split_once('=') {
+            Some((key, value)) => KernelParameter {
+                key: key.into(),
+                value: Some(value.into()),
+            },
+            None => KernelParameter {
+                key: parameter,
+                value: None,
+            },
+        })
+        .collect();
+
+    Ok(KernelCmdline {
+        cmdline,
+        parameters,
+    })
+}
+
+// Keys use dots as separators, dots in file names (e.g: VLAN interfaces) are shown as slashes
+fn walk_sysctl(root: &Path, path: &Path, sysctls: &mut Vec<Sysctl>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let entry_path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            walk_sysctl(root, &entry_path, sysctls);
+            continue;
+        }
+
+        // Write-only entries, like vm.drop_caches, fail to be read
+        let Ok(value) = fs::read_to_string(&entry_path) else {
+            continue;
+        };
+        let key = entry_path
+            .strip_prefix(root)
+            .unwrap_or(&entry_path)
+            .iter()
+            .map(|component| component.to_string_lossy().replace('.', "/"))
+            .collect::<Vec<String>>()
+            .join(".");
+        sysctls.push(Sysctl {
+            key,
+            value: value.trim().to_string(),
+        });
+    }
+}
+
+// Prefixes match whole segments, e.g: net.ipv4 matches net.ipv4.ip_forward but not net.ipv4x.value
+fn key_matches(key: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || key == prefix
+        || key
+            .strip_prefix(prefix)
+            .map_or(false, |rest| rest.starts_with('.'))
+}
+
+pub fn sysctl(prefix: Option<String>) -> Result<Vec<Sysctl>, String> {
+    let root = Path::new(SYSCTL_PATH);
+    let prefix = prefix.unwrap_or_default();
+    let prefix = prefix.trim_end_matches('.');
+
+    // Only walk the top level directory of the prefix, e.g: net for net.ipv4
+    let top_level = prefix.split('.').next().unwrap_or_default();
+    let is_valid = top_level
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !is_valid {
+        return Err(format!("Invalid sysctl prefix: {prefix}"));
+    }
+
+    let path = root.join(top_level);
+    if !path.exists() {
+        return Err(format!("No sysctl found with prefix: {prefix}"));
+    }
+
+    let mut sysctls = vec![];
+    walk_sysctl(root, &path, &mut sysctls);
+    sysctls.retain(|sysctl| key_matches(&sysctl.key, prefix));
+    sysctls.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(sysctls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_key_segments() {
+        assert!(key_matches("net.ipv4.ip_forward", ""));
+        assert!(key_matches("net.ipv4.ip_forward", "net"));
+        assert!(key_matches("net.ipv4.ip_forward", "net.ipv4"));
+        assert!(key_matches("net.ipv4.ip_forward", "net.ipv4.ip_forward"));
+        assert!(key_matches("kernel.pid_max", "kernel.pid_max"));
+        assert!(!key_matches("kernel.pid_max", "kernel.pid"));
+        assert!(!key_matches("net.ipv4x.value", "net.ipv4"));
+        assert!(!key_matches("net.ipv4", "net.ipv4.ip_forward"));
+    }
+}
@@ -1,10 +1,28 @@
+pub mod block_devices;
+pub mod cgroups;
+pub mod containers;
+pub mod containers_websocket;
+pub mod cpufreq;
+pub mod health;
 pub mod journal;
 pub mod journal_websocket;
 pub mod kernel;
+pub mod kernel_info;
 pub mod kernel_websocket;
 pub mod model;
-pub mod netstat;
+pub mod mounts;
+pub mod network;
 pub mod platform;
+pub mod power;
+pub mod sensors;
 pub mod serial;
+pub mod serial_hotplug;
+pub mod serial_hotplug_websocket;
+pub mod serial_websocket;
 pub mod system;
+pub mod systemd;
+pub mod thermal;
+pub mod time;
 pub mod udev;
+pub mod users;
+pub mod wifi;
@@ -0,0 +1,215 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+use tracing::*;
+
+const READ_ONLY_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+static ONCE: Once = Once::new();
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct MountUsage {
+    total_B: u64,
+    free_B: u64,
+    available_B: u64,
+    total_inodes: u64,
+    free_inodes: u64,
+    available_inodes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Mount {
+    mount_id: u32,
+    parent_id: u32,
+    device: String,
+    root: String,
+    mount_point: String,
+    filesystem_type: String,
+    source: String,
+    mount_options: Vec<String>,
+    super_options: Vec<String>,
+    read_only: bool,
+    // Not provided for network and FUSE filesystems
+    usage: Option<MountUsage>,
+}
+
+// Mount points and sources escape spaces, tabs, new lines and backslashes as octal, e.g: \040
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 4)
+            .filter(|_| bytes[index] == b'\\')
+            .and_then(|octal| std::str::from_utf8(octal).ok())
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        match escaped {
+            Some(byte) => {
+                result.push(byte);
+                index += 4;
+            }
+            None => {
+                result.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+// statvfs waits for the server or the FUSE daemon to answer, forever on a stale NFS mount
+fn is_blocking_filesystem(filesystem_type: &str) -> bool {
+    matches!(
+        filesystem_type,
+        "nfs" | "nfs4" | "cifs" | "smb3" | "smbfs" | "ncpfs" | "afs" | "9p" | "ceph" | "glusterfs"
+    ) || filesystem_type.starts_with("fuse")
+}
+
+fn statvfs(path: &str) -> Option<MountUsage> {
+    let path = CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    let fragment_size = stat.f_frsize as u64;
+    Some(MountUsage {
+        total_B: stat.f_blocks as u64 * fragment_size,
+        free_B: stat.f_bfree as u64 * fragment_size,
+        available_B: stat.f_bavail as u64 * fragment_size,
+        total_inodes: stat.f_files as u64,
+        free_inodes: stat.f_ffree as u64,
+        available_inodes: stat.f_favail as u64,
+    })
+}
+
+// Format: mount_id parent_id major:minor root mount_point options [optional fields...] - fstype source super_options
+fn parse_mountinfo_line(line: &str) -> Option<Mount> {
+    let (mount_fields, filesystem_fields) = line.split_once(" - ")?;
+
+    let mut mount_fields = mount_fields.split_whitespace();
+    let mount_id = mount_fields.next()?.parse().ok()?;
+    let parent_id = mount_fields.next()?.parse().ok()?;
+    let device = mount_fields.next()?.to_string();
+    let root = unescape(mount_fields.next()?);
+    let mount_point = unescape(mount_fields.next()?);
+    let mount_options = mount_fields
+        .next()?
+        .split(',')
+        .map(String::from)
+        .collect::<Vec<String>>();
+
+    let mut filesystem_fields = filesystem_fields.split_whitespace();
+    let filesystem_type = filesystem_fields.next()?.to_string();
+    let source = unescape(filesystem_fields.next().unwrap_or_default());
+    let super_options = filesystem_fields
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .map(String::from)
+        .collect::<Vec<String>>();
+
+    // A filesystem remounted read-only after errors only shows it in the super block options
+    let read_only = mount_options.iter().any(|option| option == "ro")
+        || super_options.iter().any(|option| option == "ro");
+
+    Some(Mount {
+        mount_id,
+        parent_id,
+        device,
+        root,
+        mount_point,
+        filesystem_type,
+        source,
+        mount_options,
+        super_options,
+        read_only,
+        usage: None,
+    })
+}
+
+fn read_mountinfo() -> Result<Vec<Mount>, String> {
+    let content = std::fs::read_to_string("/proc/self/mountinfo")
+        .map_err(|error| format!("Failed to read /proc/self/mountinfo: {error}"))?;
+
+    Ok(content.lines().filter_map(parse_mountinfo_line).collect())
+}
+
+#[cached(time = 5)]
+pub fn mounts() -> Result<Vec<Mount>, String> {
+    Ok(read_mountinfo()?
+        .into_iter()
+        .map(|mut mount| {
+            if !is_blocking_filesystem(&mount.filesystem_type) {
+                mount.usage = statvfs(&mount.mount_point);
+            }
+            mount
+        })
+        .collect())
+}
+
+// Watch for filesystems going read-only, a common failure with SD cards
+pub fn start() {
+    ONCE.call_once(|| {
+        thread::spawn(|| {
+            // Remounting keeps the mount id, so it can be used to detect mounts that changed
+            let mut previous_mounts: Option<HashMap<u32, Mount>> = None;
+            loop {
+                match read_mountinfo() {
+                    Ok(mounts) => {
+                        if let Some(previous_mounts) = &previous_mounts {
+                            for mount in &mounts {
+                                let Some(previous) = previous_mounts.get(&mount.mount_id) else {
+                                    continue;
+                                };
+                                let (mount_point, source) = (&mount.mount_point, &mount.source);
+                                if mount.read_only && !previous.read_only {
+                                    warn!("Filesystem {mount_point} ({source}) is now read-only");
+                                } else if !mount.read_only && previous.read_only {
+                                    info!("Filesystem {mount_point} ({source}) is now read-write");
+                                }
+                            }
+                        }
+                        previous_mounts = Some(
+                            mounts
+                                .into_iter()
+                                .map(|mount| (mount.mount_id, mount))
+                                .collect(),
+                        );
+                    }
+                    Err(error) => warn!("{error}"),
+                }
+                thread::sleep(READ_ONLY_WATCH_INTERVAL);
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_network_and_fuse_filesystems() {
+        for filesystem_type in ["nfs", "nfs4", "cifs", "fuse", "fuse.sshfs", "fuseblk", "9p"] {
+            assert!(
+                is_blocking_filesystem(filesystem_type),
+                "{}",
+                filesystem_type
+            );
+        }
+        for filesystem_type in ["ext4", "vfat", "tmpfs", "overlay", "squashfs", "proc"] {
+            assert!(
+                !is_blocking_filesystem(filesystem_type),
+                "{}",
+                filesystem_type
+            );
+        }
+    }
+}
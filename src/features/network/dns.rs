@@ -0,0 +1,73 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+// Contains the upstream servers used by systemd-resolved instead of its local stub
+const SYSTEMD_RESOLVED_RESOLV_CONF_PATH: &str = "/run/systemd/resolve/resolv.conf";
+const SYSTEMD_RESOLVED_STUB_ADDRESSES: [&str; 2] = ["127.0.0.53", "127.0.0.54"];
+
+#[derive(Clone, Debug, Default, Serialize, Apiv2Schema)]
+pub struct ResolvConf {
+    path: String,
+    nameservers: Vec<String>,
+    search: Vec<String>,
+    options: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Dns {
+    resolv_conf: Option<ResolvConf>,
+    uses_systemd_resolved: bool,
+    systemd_resolved: Option<ResolvConf>,
+}
+
+impl ResolvConf {
+    fn from_file(path: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut resolv_conf = ResolvConf {
+            path: path.into(),
+            ..Default::default()
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => resolv_conf.nameservers.extend(fields.map(String::from)),
+                // domain and search are mutually exclusive, the last one wins
+                Some("search") | Some("domain") => {
+                    resolv_conf.search = fields.map(String::from).collect()
+                }
+                Some("options") => resolv_conf.options.extend(fields.map(String::from)),
+                _ => (),
+            }
+        }
+
+        Some(resolv_conf)
+    }
+}
+
+#[cached(time = 5)]
+pub fn dns() -> Dns {
+    let resolv_conf = ResolvConf::from_file(RESOLV_CONF_PATH);
+    let uses_systemd_resolved = resolv_conf
+        .as_ref()
+        .map(|resolv_conf| {
+            resolv_conf
+                .nameservers
+                .iter()
+                .any(|nameserver| SYSTEMD_RESOLVED_STUB_ADDRESSES.contains(&nameserver.as_str()))
+        })
+        .unwrap_or(false);
+
+    Dns {
+        resolv_conf,
+        uses_systemd_resolved,
+        systemd_resolved: ResolvConf::from_file(SYSTEMD_RESOLVED_RESOLV_CONF_PATH),
+    }
+}
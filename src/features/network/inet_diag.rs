@@ -0,0 +1,263 @@
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use tracing::*;
+
+// From linux/netlink.h, linux/sock_diag.h and linux/inet_diag.h
+const NETLINK_SOCK_DIAG: i32 = 4;
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+const NLMSG_ERROR: u16 = 0x2;
+const NLMSG_DONE: u16 = 0x3;
+const INET_DIAG_INFO: u16 = 2;
+const NLMSG_HEADER_SIZE: usize = 16;
+const INET_DIAG_MSG_SIZE: usize = 72;
+const RTA_HEADER_SIZE: usize = 4;
+
+#[derive(Clone, Debug, Default, Serialize, Apiv2Schema)]
+pub struct TcpDetails {
+    receive_queue_B: u32,
+    send_queue_B: u32,
+    rtt_ms: f32,
+    rtt_variance_ms: f32,
+    retransmits: u8,
+    total_retransmits: u32,
+    lost_packets: u32,
+    congestion_window: u32,
+    slow_start_threshold: u32,
+    mss_B: u32,
+    pub(crate) bytes_acked: u64,
+    pub(crate) bytes_received: u64,
+    bytes_sent: u64,
+    bytes_retransmitted: u64,
+    delivery_rate_B_per_second: u64,
+}
+
+pub struct TcpSocket {
+    pub(crate) local: SocketAddr,
+    pub(crate) remote: SocketAddr,
+    pub(crate) inode: u64,
+    pub(crate) details: TcpDetails,
+}
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn u16_at(buffer: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_ne_bytes(
+        buffer.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(buffer: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_ne_bytes(
+        buffer.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u64_at(buffer: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_ne_bytes(
+        buffer.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn request(family: u8) -> Vec<u8> {
+    let length = NLMSG_HEADER_SIZE + 56;
+    let mut request = Vec::with_capacity(length);
+    // struct nlmsghdr
+    request.extend_from_slice(&(length as u32).to_ne_bytes());
+    request.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes());
+    request.extend_from_slice(&0u32.to_ne_bytes());
+    // struct inet_diag_req_v2: family, protocol, extensions, padding and all states
+    request.extend_from_slice(&[
+        family,
+        libc::IPPROTO_TCP as u8,
+        1 << (INET_DIAG_INFO - 1),
+        0,
+    ]);
+    request.extend_from_slice(&u32::MAX.to_ne_bytes());
+    // struct inet_diag_sockid, zeroed to match all sockets
+    request.resize(length, 0);
+    request
+}
+
+// struct inet_diag_sockid addresses are always 16 bytes in network order
+fn address(family: u8, bytes: &[u8]) -> Option<IpAddr> {
+    if family == libc::AF_INET as u8 {
+        let octets: [u8; 4] = bytes.get(0..4)?.try_into().ok()?;
+        return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+    }
+    let octets: [u8; 16] = bytes.get(0..16)?.try_into().ok()?;
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+// Parses struct inet_diag_msg followed by its attributes, where INET_DIAG_INFO holds struct tcp_info
+fn parse_message(message: &[u8]) -> Option<TcpSocket> {
+    if message.len() < INET_DIAG_MSG_SIZE {
+        return None;
+    }
+
+    let family = message[0];
+    let source_port = u16::from_be_bytes([message[4], message[5]]);
+    let destination_port = u16::from_be_bytes([message[6], message[7]]);
+    let source = SocketAddr::new(address(family, &message[8..24])?, source_port);
+    let destination = SocketAddr::new(address(family, &message[24..40])?, destination_port);
+
+    let inode = u32_at(message, 68)? as u64;
+
+    let mut details = TcpDetails {
+        receive_queue_B: u32_at(message, 56)?,
+        send_queue_B: u32_at(message, 60)?,
+        ..Default::default()
+    };
+
+    let mut offset = INET_DIAG_MSG_SIZE;
+    while offset + RTA_HEADER_SIZE <= message.len() {
+        let length = u16_at(message, offset)? as usize;
+        let attribute_type = u16_at(message, offset + 2)?;
+        if length < RTA_HEADER_SIZE {
+            break;
+        }
+
+        if attribute_type == INET_DIAG_INFO {
+            let end = (offset + length).min(message.len());
+            let info = &message[offset + RTA_HEADER_SIZE..end];
+            // Older kernels provide a shorter struct tcp_info, missing fields stay zeroed
+            details.retransmits = info.get(2).copied().unwrap_or_default();
+            details.mss_B = u32_at(info, 16).unwrap_or_default();
+            details.lost_packets = u32_at(info, 32).unwrap_or_default();
+            details.rtt_ms = u32_at(info, 68).unwrap_or_default() as f32 / 1000.0;
+            details.rtt_variance_ms = u32_at(info, 72).unwrap_or_default() as f32 / 1000.0;
+            details.slow_start_threshold = u32_at(info, 76).unwrap_or_default();
+            details.congestion_window = u32_at(info, 80).unwrap_or_default();
+            details.total_retransmits = u32_at(info, 100).unwrap_or_default();
+            details.bytes_acked = u64_at(info, 120).unwrap_or_default();
+            details.bytes_received = u64_at(info, 128).unwrap_or_default();
+            details.delivery_rate_B_per_second = u64_at(info, 160).unwrap_or_default();
+            details.bytes_sent = u64_at(info, 200).unwrap_or_default();
+            details.bytes_retransmitted = u64_at(info, 208).unwrap_or_default();
+        }
+
+        offset += align(length);
+    }
+
+    Some(TcpSocket {
+        local: source,
+        remote: destination,
+        inode,
+        details,
+    })
+}
+
+fn dump(family: u8) -> Result<Vec<TcpSocket>, String> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            NETLINK_SOCK_DIAG,
+        )
+    };
+    if fd < 0 {
+        return Err(format!(
+            "Failed to open sock_diag socket: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut kernel: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    kernel.nl_family = libc::AF_NETLINK as u16;
+    let request = request(family);
+    let sent = unsafe {
+        libc::sendto(
+            socket.as_raw_fd(),
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+            &kernel as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as u32,
+        )
+    };
+    if sent < 0 {
+        return Err(format!(
+            "Failed to send inet_diag request: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let mut sockets = vec![];
+    let mut buffer = vec![0u8; 32 * 1024];
+    loop {
+        let received = unsafe {
+            libc::recv(
+                socket.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                0,
+            )
+        };
+        if received < 0 {
+            return Err(format!(
+                "Failed to receive inet_diag response: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        let data = &buffer[..received as usize];
+        let mut offset = 0;
+        while offset + NLMSG_HEADER_SIZE <= data.len() {
+            let length = u32_at(data, offset).unwrap_or_default() as usize;
+            let message_type = u16_at(data, offset + 4).unwrap_or_default();
+            if length < NLMSG_HEADER_SIZE || offset + length > data.len() {
+                return Err("Malformed inet_diag response".into());
+            }
+
+            let payload = &data[offset + NLMSG_HEADER_SIZE..offset + length];
+            match message_type {
+                NLMSG_DONE => return Ok(sockets),
+                NLMSG_ERROR => {
+                    let error = u32_at(payload, 0).unwrap_or_default() as i32;
+                    if error != 0 {
+                        return Err(format!(
+                            "inet_diag request failed: {}",
+                            std::io::Error::from_raw_os_error(-error)
+                        ));
+                    }
+                }
+                _ => {
+                    if let Some(socket) = parse_message(payload) {
+                        sockets.push(socket);
+                    }
+                }
+            }
+
+            offset += align(length);
+        }
+    }
+}
+
+// Dumps all IPv4 and IPv6 TCP sockets with their inodes and details
+pub fn tcp_sockets() -> Result<Vec<TcpSocket>, String> {
+    let mut sockets = dump(libc::AF_INET as u8)?;
+    // Not available when IPv6 is disabled
+    match dump(libc::AF_INET6 as u8) {
+        Ok(ipv6_sockets) => sockets.extend(ipv6_sockets),
+        Err(error) => debug!("{error}"),
+    }
+    Ok(sockets)
+}
+
+// Provides TCP socket details keyed by local and remote addresses
+pub fn tcp_details() -> Result<HashMap<(SocketAddr, SocketAddr), TcpDetails>, String> {
+    Ok(tcp_sockets()?
+        .into_iter()
+        .map(|socket| ((socket.local, socket.remote), socket.details))
+        .collect())
+}
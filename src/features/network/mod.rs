@@ -0,0 +1,6 @@
+pub mod dns;
+pub mod inet_diag;
+pub mod neighbors;
+pub mod netstat;
+pub mod process_traffic;
+pub mod routes;
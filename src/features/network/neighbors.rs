@@ -0,0 +1,116 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use serde_json::Value;
+use std::process::Command;
+use tracing::*;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Neighbor {
+    family: String,
+    address: String,
+    interface: String,
+    mac: Option<String>,
+    state: Vec<String>,
+    is_router: bool,
+}
+
+// Neighbor states are only exposed over netlink, iproute2 provides them as json
+fn from_iproute() -> Result<Vec<Neighbor>, String> {
+    let output = Command::new("ip")
+        .args(["-json", "neighbor", "show"])
+        .output()
+        .map_err(|error| format!("Failed to run ip: {error}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "ip neighbor failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let entries: Vec<Value> = serde_json::from_slice(&output.stdout)
+        .map_err(|error| format!("Invalid ip neighbor json payload: {error}"))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let address = entry.get("dst")?.as_str()?.to_string();
+            Some(Neighbor {
+                family: if address.contains(':') {
+                    "ipv6"
+                } else {
+                    "ipv4"
+                }
+                .into(),
+                address,
+                interface: entry
+                    .get("dev")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .into(),
+                mac: entry
+                    .get("lladdr")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                state: entry
+                    .get("state")
+                    .and_then(|v| v.as_array())
+                    .map(|states| {
+                        states
+                            .iter()
+                            .filter_map(|state| state.as_str())
+                            .map(|state| state.to_lowercase())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                is_router: entry.get("router").is_some(),
+            })
+        })
+        .collect())
+}
+
+// Fallback for systems without iproute2, IPv4 only
+// Format: IP address HW type Flags HW address Mask Device
+fn from_proc_arp() -> Vec<Neighbor> {
+    let content = match std::fs::read_to_string("/proc/net/arp") {
+        Ok(content) => content,
+        Err(error) => {
+            warn!("Failed to read /proc/net/arp: {error}");
+            return vec![];
+        }
+    };
+
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let flags = u32::from_str_radix(fields.get(2)?.trim_start_matches("0x"), 16).ok()?;
+            // ATF_COM: completed entry, ATF_PERM: permanent entry
+            let state = match (flags & 0x2 != 0, flags & 0x4 != 0) {
+                (_, true) => "permanent",
+                (true, false) => "reachable",
+                (false, false) => "incomplete",
+            };
+            let mac = fields.get(3)?.to_string();
+
+            Some(Neighbor {
+                family: "ipv4".into(),
+                address: fields.first()?.to_string(),
+                interface: fields.get(5)?.to_string(),
+                mac: (mac != "00:00:00:00:00:00").then_some(mac),
+                state: vec![state.into()],
+                is_router: false,
+            })
+        })
+        .collect()
+}
+
+#[cached(time = 5)]
+pub fn neighbors() -> Vec<Neighbor> {
+    from_iproute().unwrap_or_else(|error| {
+        debug!("{error}, falling back to /proc/net/arp");
+        from_proc_arp()
+    })
+}
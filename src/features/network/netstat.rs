@@ -0,0 +1,513 @@
+use crate::features::network::inet_diag;
+use netstat2::{AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo};
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+
+// Suffixes of systemd units that own processes, slices only group them
+const UNIT_SUFFIXES: [&str; 4] = [".service", ".scope", ".socket", ".mount"];
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+struct AddressPort {
+    address: String,
+    port: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct SocketProcess {
+    pid: u32,
+    name: String,
+    executable: Option<String>,
+    user: Option<String>,
+    unit: Option<String>,
+}
+
+impl SocketProcess {
+    pub(crate) fn from(pid: u32, users: &HashMap<u32, String>) -> Self {
+        let path = std::path::Path::new("/proc").join(pid.to_string());
+
+        let uid = std::fs::read_to_string(path.join("status"))
+            .ok()
+            .and_then(|status| {
+                // Uid: real effective saved filesystem
+                status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Uid:"))
+                    .and_then(|uids| uids.split_whitespace().next())
+                    .and_then(|uid| uid.parse::<u32>().ok())
+            });
+
+        // cgroup v2: 0::/system.slice/ssh.service, v1 has one line per controller
+        let unit = std::fs::read_to_string(path.join("cgroup"))
+            .ok()
+            .and_then(|cgroup| {
+                cgroup.lines().find_map(|line| {
+                    line.rsplit(':')
+                        .next()?
+                        .rsplit('/')
+                        .find(|name| UNIT_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)))
+                        .map(String::from)
+                })
+            });
+
+        SocketProcess {
+            pid,
+            name: std::fs::read_to_string(path.join("comm"))
+                .map(|name| name.trim().to_string())
+                .unwrap_or_default(),
+            executable: std::fs::read_link(path.join("exe"))
+                .ok()
+                .map(|exe| exe.to_string_lossy().to_string()),
+            user: uid.map(|uid| users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())),
+            unit,
+        }
+    }
+}
+
+// Maps user ids to names from /etc/passwd, name:password:uid:gid:gecos:home:shell
+pub(crate) fn users() -> HashMap<u32, String> {
+    std::fs::read_to_string("/etc/passwd")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?.to_string();
+            let uid = fields.nth(1)?.parse::<u32>().ok()?;
+            Some((uid, name))
+        })
+        .collect()
+}
+
+fn processes_from_pids(pids: &HashSet<u32>) -> HashMap<u32, SocketProcess> {
+    let users = users();
+    pids.iter()
+        .map(|pid| (*pid, SocketProcess::from(*pid, &users)))
+        .collect()
+}
+
+fn family(address: &IpAddr) -> String {
+    match address {
+        IpAddr::V4(_) => "ipv4".into(),
+        IpAddr::V6(_) => "ipv6".into(),
+    }
+}
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+struct Udp {
+    family: String,
+    local: AddressPort,
+    pids: Vec<u32>,
+    processes: Vec<SocketProcess>,
+}
+
+impl Udp {
+    fn new(udp: &netstat2::UdpSocketInfo, socket_info: &netstat2::SocketInfo) -> Udp {
+        Udp {
+            family: family(&udp.local_addr),
+            local: AddressPort {
+                address: udp.local_addr.to_string(),
+                port: udp.local_port,
+            },
+            pids: socket_info.associated_pids.clone(),
+            processes: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+struct Tcp {
+    family: String,
+    local: AddressPort,
+    remote: AddressPort,
+    pids: Vec<u32>,
+    processes: Vec<SocketProcess>,
+    state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<inet_diag::TcpDetails>,
+}
+
+impl Tcp {
+    fn new(tcp: &netstat2::TcpSocketInfo, socket_info: &netstat2::SocketInfo) -> Tcp {
+        Tcp {
+            family: family(&tcp.local_addr),
+            local: AddressPort {
+                address: tcp.local_addr.to_string(),
+                port: tcp.local_port,
+            },
+            remote: AddressPort {
+                address: tcp.remote_addr.to_string(),
+                port: tcp.remote_port,
+            },
+            pids: socket_info.associated_pids.clone(),
+            processes: vec![],
+            state: format!("{}", tcp.state),
+            details: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+struct Unix {
+    path: Option<String>,
+    #[serde(rename = "type")]
+    socket_type: String,
+    state: String,
+    inode: u64,
+    pids: Vec<u32>,
+    processes: Vec<SocketProcess>,
+}
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+pub struct Netstat {
+    tcp: Vec<Tcp>,
+    udp: Vec<Udp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unix: Option<Vec<Unix>>,
+}
+
+// Maps socket inodes to the processes holding them, from /proc/<pid>/fd/* -> socket:[inode]
+pub(crate) fn socket_inode_pids() -> HashMap<u64, Vec<u32>> {
+    let mut inode_pids: HashMap<u64, Vec<u32>> = HashMap::new();
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return inode_pids;
+    };
+
+    for process in processes.filter_map(|entry| entry.ok()) {
+        let Ok(pid) = process.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+
+        for fd in fds.filter_map(|entry| entry.ok()) {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let Some(inode) = target
+                .to_string_lossy()
+                .strip_prefix("socket:[")
+                .and_then(|inode| inode.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok())
+            else {
+                continue;
+            };
+
+            let pids = inode_pids.entry(inode).or_default();
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+
+    inode_pids
+}
+
+// Format: Num RefCount Protocol Flags Type St Inode [Path]
+fn unix_sockets() -> Result<Vec<Unix>, String> {
+    let content = std::fs::read_to_string("/proc/net/unix")
+        .map_err(|error| format!("Failed to read /proc/net/unix: {error}"))?;
+    let inode_pids = socket_inode_pids();
+
+    Ok(content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let socket_type = match *fields.get(4)? {
+                "0001" => "stream",
+                "0002" => "dgram",
+                "0005" => "seqpacket",
+                _ => "unknown",
+            };
+            // __SO_ACCEPTCON marks listening sockets
+            let state = match (*fields.get(5)?, flags & 0x10000 != 0) {
+                ("01", true) => "LISTEN",
+                ("01", false) => "UNCONNECTED",
+                ("02", _) => "CONNECTING",
+                ("03", _) => "CONNECTED",
+                ("04", _) => "DISCONNECTING",
+                _ => "UNKNOWN",
+            };
+            let inode = fields.get(6)?.parse::<u64>().ok()?;
+
+            Some(Unix {
+                // Paths starting with @ are in the abstract namespace
+                path: fields.get(7).map(|path| path.to_string()),
+                socket_type: socket_type.into(),
+                state: state.into(),
+                inode,
+                pids: inode_pids.get(&inode).cloned().unwrap_or_default(),
+                processes: vec![],
+            })
+        })
+        .collect())
+}
+
+fn state_matches(state: &str, filter: &Option<String>) -> bool {
+    let normalize = |state: &str| state.to_uppercase().replace('-', "_");
+    filter
+        .as_ref()
+        .map(|filter| normalize(state) == normalize(filter))
+        .unwrap_or(true)
+}
+
+fn pid_matches(pids: &[u32], filter: Option<u32>) -> bool {
+    filter.map(|pid| pids.contains(&pid)).unwrap_or(true)
+}
+
+// UDP sockets have no state, they are bound to receive datagrams like listening TCP sockets,
+// so they only match the LISTEN state filter
+fn inet_sockets(
+    sockets_info: &[netstat2::SocketInfo],
+    state: &Option<String>,
+    port: Option<u16>,
+    pid: Option<u32>,
+) -> (Vec<Tcp>, Vec<Udp>) {
+    let port_matches = |ports: &[u16]| port.map(|port| ports.contains(&port)).unwrap_or(true);
+
+    let tcps = sockets_info
+        .iter()
+        .filter_map(|socket_info| match &socket_info.protocol_socket_info {
+            ProtocolSocketInfo::Tcp(tcp) => Some((socket_info.clone(), tcp)),
+            ProtocolSocketInfo::Udp(_udp) => None,
+        })
+        .map(|(socket_info, tcp)| Tcp::new(tcp, &socket_info))
+        .filter(|tcp| {
+            state_matches(&tcp.state, state)
+                && port_matches(&[tcp.local.port, tcp.remote.port])
+                && pid_matches(&tcp.pids, pid)
+        })
+        .collect::<Vec<Tcp>>();
+
+    let udps = sockets_info
+        .iter()
+        .filter_map(|socket_info| match &socket_info.protocol_socket_info {
+            ProtocolSocketInfo::Tcp(_tcp) => None,
+            ProtocolSocketInfo::Udp(udp) => Some((socket_info.clone(), udp)),
+        })
+        .map(|(socket_info, udp)| Udp::new(udp, &socket_info))
+        .filter(|udp| {
+            state_matches("LISTEN", state)
+                && port_matches(&[udp.local.port])
+                && pid_matches(&udp.pids, pid)
+        })
+        .collect::<Vec<Udp>>();
+
+    (tcps, udps)
+}
+
+pub fn netstat(
+    state: Option<String>,
+    port: Option<u16>,
+    pid: Option<u32>,
+    unix: Option<bool>,
+    details: Option<bool>,
+) -> Result<Netstat, String> {
+    let sockets_info = netstat2::get_sockets_info(
+        AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+        ProtocolFlags::TCP | ProtocolFlags::UDP,
+    )
+    .map_err(|error| format!("Failed to get sockets information: {error}"))?;
+    let (mut tcps, udps) = inet_sockets(&sockets_info, &state, port, pid);
+
+    // Unix sockets have no port, so they do not match any port filter
+    let unixes = if unix.unwrap_or(false) {
+        Some(
+            unix_sockets()?
+                .into_iter()
+                .filter(|unix| {
+                    port.is_none()
+                        && state_matches(&unix.state, &state)
+                        && pid_matches(&unix.pids, pid)
+                })
+                .collect::<Vec<Unix>>(),
+        )
+    } else {
+        None
+    };
+
+    if details.unwrap_or(false) {
+        let tcp_details = inet_diag::tcp_details()?;
+        for tcp in &mut tcps {
+            let local = tcp.local.address.parse::<IpAddr>().ok();
+            let remote = tcp.remote.address.parse::<IpAddr>().ok();
+            if let (Some(local), Some(remote)) = (local, remote) {
+                tcp.details = tcp_details
+                    .get(&(
+                        SocketAddr::new(local, tcp.local.port),
+                        SocketAddr::new(remote, tcp.remote.port),
+                    ))
+                    .cloned();
+            }
+        }
+    }
+
+    let mut netstat = Netstat {
+        tcp: tcps,
+        udp: udps,
+        unix: unixes,
+    };
+    netstat.resolve_processes();
+
+    Ok(netstat)
+}
+
+impl Netstat {
+    fn resolve_processes(&mut self) {
+        let pids = self
+            .tcp
+            .iter()
+            .flat_map(|tcp| tcp.pids.iter())
+            .chain(self.udp.iter().flat_map(|udp| udp.pids.iter()))
+            .chain(self.unix.iter().flatten().flat_map(|unix| unix.pids.iter()))
+            .copied()
+            .collect::<HashSet<u32>>();
+        let processes = processes_from_pids(&pids);
+        let resolve = |pids: &[u32]| {
+            pids.iter()
+                .filter_map(|pid| processes.get(pid).cloned())
+                .collect::<Vec<SocketProcess>>()
+        };
+
+        for tcp in &mut self.tcp {
+            tcp.processes = resolve(&tcp.pids);
+        }
+        for udp in &mut self.udp {
+            udp.processes = resolve(&udp.pids);
+        }
+        for unix in self.unix.iter_mut().flatten() {
+            unix.processes = resolve(&unix.pids);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Apiv2Schema)]
+pub struct ListeningPort {
+    protocol: String,
+    family: String,
+    address: String,
+    port: u16,
+    processes: Vec<SocketProcess>,
+}
+
+// Summary of the TCP ports waiting for connections and UDP ports bound to receive data
+pub fn listening() -> Result<Vec<ListeningPort>, String> {
+    let netstat = netstat(None, None, None, None, None)?;
+
+    let mut ports = netstat
+        .tcp
+        .into_iter()
+        .filter(|tcp| state_matches(&tcp.state, &Some("LISTEN".into())))
+        .map(|tcp| ListeningPort {
+            protocol: "tcp".into(),
+            family: tcp.family,
+            address: tcp.local.address,
+            port: tcp.local.port,
+            processes: tcp.processes,
+        })
+        .chain(netstat.udp.into_iter().map(|udp| ListeningPort {
+            protocol: "udp".into(),
+            family: udp.family,
+            address: udp.local.address,
+            port: udp.local.port,
+            processes: udp.processes,
+        }))
+        .collect::<Vec<ListeningPort>>();
+
+    ports.sort_by(|a, b| (a.port, &a.protocol).cmp(&(b.port, &b.protocol)));
+    Ok(ports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netstat2::{TcpSocketInfo, TcpState, UdpSocketInfo};
+
+    fn socket_info(protocol_socket_info: ProtocolSocketInfo, pid: u32) -> netstat2::SocketInfo {
+        netstat2::SocketInfo {
+            protocol_socket_info,
+            associated_pids: vec![pid],
+            inode: 0,
+            uid: 0,
+        }
+    }
+
+    fn tcp(local_port: u16, remote_port: u16, state: TcpState, pid: u32) -> netstat2::SocketInfo {
+        socket_info(
+            ProtocolSocketInfo::Tcp(TcpSocketInfo {
+                local_addr: "0.0.0.0".parse().unwrap(),
+                local_port,
+                remote_addr: "0.0.0.0".parse().unwrap(),
+                remote_port,
+                state,
+            }),
+            pid,
+        )
+    }
+
+    fn udp(local_port: u16, pid: u32) -> netstat2::SocketInfo {
+        socket_info(
+            ProtocolSocketInfo::Udp(UdpSocketInfo {
+                local_addr: "::".parse().unwrap(),
+                local_port,
+            }),
+            pid,
+        )
+    }
+
+    #[test]
+    fn filters_tcp_and_udp_sockets() {
+        let sockets_info = vec![
+            tcp(8080, 0, TcpState::Listen, 10),
+            tcp(8080, 51000, TcpState::Established, 10),
+            udp(14550, 20),
+            udp(5353, 30),
+        ];
+        let ports = |(tcps, udps): (Vec<Tcp>, Vec<Udp>)| {
+            (
+                tcps.iter()
+                    .map(|tcp| (tcp.local.port, tcp.state.clone()))
+                    .collect::<Vec<(u16, String)>>(),
+                udps.iter().map(|udp| udp.local.port).collect::<Vec<u16>>(),
+            )
+        };
+
+        let (tcps, udps) = ports(inet_sockets(&sockets_info, &None, None, None));
+        assert_eq!(tcps.len(), 2);
+        assert_eq!(udps, vec![14550, 5353]);
+
+        let (tcps, udps) = ports(inet_sockets(
+            &sockets_info,
+            &Some("listen".into()),
+            None,
+            None,
+        ));
+        assert_eq!(tcps, vec![(8080, "LISTEN".to_string())]);
+        assert_eq!(udps, vec![14550, 5353]);
+
+        let (tcps, udps) = ports(inet_sockets(
+            &sockets_info,
+            &Some("ESTABLISHED".into()),
+            None,
+            None,
+        ));
+        assert_eq!(tcps, vec![(8080, "ESTABLISHED".to_string())]);
+        assert!(udps.is_empty());
+
+        let (tcps, udps) = ports(inet_sockets(&sockets_info, &None, Some(14550), None));
+        assert!(tcps.is_empty());
+        assert_eq!(udps, vec![14550]);
+
+        let (tcps, udps) = ports(inet_sockets(
+            &sockets_info,
+            &Some("LISTEN".into()),
+            None,
+            Some(30),
+        ));
+        assert!(tcps.is_empty());
+        assert_eq!(udps, vec![5353]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::features::network::{inet_diag, netstat};
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::*;
+
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+// Sampling walks every /proc/<pid>/fd, so it only runs while someone is asking for it,
+// the timeout is long enough to keep sampling for clients polling every few minutes
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ProcessTraffic {
+    process: netstat::SocketProcess,
+    sockets: usize,
+    // Bytes transferred by the currently open sockets since they were created
+    total_received_B: u64,
+    total_sent_B: u64,
+    received_B_per_second: f32,
+    sent_B_per_second: f32,
+    sampling_interval_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ProcessesTraffic {
+    // Rates are available after the first sampling interval, processes are empty until then
+    is_warming_up: bool,
+    processes: Vec<ProcessTraffic>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SocketCounters {
+    pid: u32,
+    received_B: u64,
+    sent_B: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProcessTotals {
+    sockets: usize,
+    received_B: u64,
+    sent_B: u64,
+    received_delta_B: u64,
+    sent_delta_B: u64,
+}
+
+struct ProcessTrafficService {
+    // None until the first sampling interval is done
+    processes: Option<Result<Vec<ProcessTraffic>, String>>,
+    last_request: Instant,
+    is_sampling: bool,
+}
+
+lazy_static! {
+    static ref PROCESS_TRAFFIC_SERVICE: Arc<Mutex<ProcessTrafficService>> =
+        Arc::new(Mutex::new(ProcessTrafficService {
+            processes: None,
+            last_request: Instant::now(),
+            is_sampling: false,
+        }));
+}
+
+// Starts sampling on the first request, the sampler stops after IDLE_TIMEOUT without requests
+pub fn process_traffic() -> Result<ProcessesTraffic, String> {
+    let mut service = PROCESS_TRAFFIC_SERVICE.lock().unwrap();
+    service.last_request = Instant::now();
+    if !service.is_sampling {
+        debug!("Starting process traffic sampling");
+        service.is_sampling = true;
+        thread::spawn(run_main_loop);
+    }
+
+    match service.processes.clone() {
+        None => Ok(ProcessesTraffic {
+            is_warming_up: true,
+            processes: vec![],
+        }),
+        Some(processes) => Ok(ProcessesTraffic {
+            is_warming_up: false,
+            processes: processes?,
+        }),
+    }
+}
+
+// Joins the TCP byte counters with the processes holding each socket, keyed by socket inode
+fn read_sockets() -> Result<HashMap<u64, SocketCounters>, String> {
+    let inode_pids = netstat::socket_inode_pids();
+    Ok(inet_diag::tcp_sockets()?
+        .into_iter()
+        // Sockets in TIME_WAIT are no longer owned by any process and have no inode
+        .filter(|socket| socket.inode != 0)
+        .filter_map(|socket| {
+            // Sockets shared after a fork are accounted to the parent, usually the lowest pid
+            let pid = inode_pids.get(&socket.inode)?.iter().min().copied()?;
+            Some((
+                socket.inode,
+                SocketCounters {
+                    pid,
+                    received_B: socket.details.bytes_received,
+                    // bytes_acked is available since 4.1 and does not count retransmissions
+                    sent_B: socket.details.bytes_acked,
+                },
+            ))
+        })
+        .collect())
+}
+
+fn process_traffic_from_samples(
+    previous: &HashMap<u64, SocketCounters>,
+    current: &HashMap<u64, SocketCounters>,
+    interval: Duration,
+) -> Vec<ProcessTraffic> {
+    let seconds = interval.as_secs_f32();
+    let rate = |value: u64| {
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        value as f32 / seconds
+    };
+
+    // Sockets opened during the interval transferred all of their bytes within it
+    let mut totals: HashMap<u32, ProcessTotals> = HashMap::new();
+    for (inode, counters) in current {
+        let previous = previous.get(inode);
+        let previous_received_B = previous.map(|previous| previous.received_B).unwrap_or(0);
+        let previous_sent_B = previous.map(|previous| previous.sent_B).unwrap_or(0);
+
+        let total = totals.entry(counters.pid).or_default();
+        total.sockets += 1;
+        total.received_B += counters.received_B;
+        total.sent_B += counters.sent_B;
+        total.received_delta_B += counters.received_B.saturating_sub(previous_received_B);
+        total.sent_delta_B += counters.sent_B.saturating_sub(previous_sent_B);
+    }
+
+    let users = netstat::users();
+    let mut processes = totals
+        .into_iter()
+        .map(|(pid, total)| ProcessTraffic {
+            process: netstat::SocketProcess::from(pid, &users),
+            sockets: total.sockets,
+            total_received_B: total.received_B,
+            total_sent_B: total.sent_B,
+            received_B_per_second: rate(total.received_delta_B),
+            sent_B_per_second: rate(total.sent_delta_B),
+            sampling_interval_ms: interval.as_millis() as u64,
+        })
+        .collect::<Vec<ProcessTraffic>>();
+
+    processes.sort_by(|a, b| {
+        (b.received_B_per_second + b.sent_B_per_second)
+            .partial_cmp(&(a.received_B_per_second + a.sent_B_per_second))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.sockets.cmp(&a.sockets))
+    });
+    processes
+}
+
+fn run_main_loop() {
+    let mut previous: Option<(HashMap<u64, SocketCounters>, Instant)> = None;
+    loop {
+        {
+            let mut service = PROCESS_TRAFFIC_SERVICE.lock().unwrap();
+            if service.last_request.elapsed() > IDLE_TIMEOUT {
+                debug!("Stopping idle process traffic sampling");
+                service.is_sampling = false;
+                service.processes = None;
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        match read_sockets() {
+            Ok(sockets) => {
+                if let Some((previous_sockets, previous_time)) = &previous {
+                    let interval = now.duration_since(*previous_time);
+                    let processes =
+                        process_traffic_from_samples(previous_sockets, &sockets, interval);
+                    PROCESS_TRAFFIC_SERVICE.lock().unwrap().processes = Some(Ok(processes));
+                }
+                previous = Some((sockets, now));
+            }
+            Err(error) => {
+                warn!("{error}");
+                PROCESS_TRAFFIC_SERVICE.lock().unwrap().processes = Some(Err(error));
+            }
+        }
+        thread::sleep(SAMPLING_INTERVAL);
+    }
+}
@@ -0,0 +1,131 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use tracing::*;
+
+// From linux/route.h and linux/ipv6_route.h
+const RTF_UP: u32 = 0x0001;
+const RTF_GATEWAY: u32 = 0x0002;
+const RTF_HOST: u32 = 0x0004;
+const RTF_DYNAMIC: u32 = 0x0010;
+const RTF_MODIFIED: u32 = 0x0020;
+const RTF_REJECT: u32 = 0x0200;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Route {
+    family: String,
+    destination: String,
+    prefix_length: u8,
+    gateway: Option<String>,
+    interface: String,
+    metric: u32,
+    is_default: bool,
+    flags: Vec<String>,
+}
+
+fn flags_from(value: u32) -> Vec<String> {
+    [
+        (RTF_UP, "up"),
+        (RTF_GATEWAY, "gateway"),
+        (RTF_HOST, "host"),
+        (RTF_DYNAMIC, "dynamic"),
+        (RTF_MODIFIED, "modified"),
+        (RTF_REJECT, "reject"),
+    ]
+    .iter()
+    .filter(|(flag, _)| value & flag != 0)
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+// Addresses are printed as the hexadecimal of the network ordered bytes read as a native integer
+fn parse_ipv4(value: &str) -> Option<Ipv4Addr> {
+    u32::from_str_radix(value, 16)
+        .ok()
+        .map(|address| Ipv4Addr::from(address.to_ne_bytes()))
+}
+
+// Format: Iface Destination Gateway Flags RefCnt Use Metric Mask MTU Window IRTT
+fn ipv4_routes() -> Vec<Route> {
+    let content = match std::fs::read_to_string("/proc/net/route") {
+        Ok(content) => content,
+        Err(error) => {
+            warn!("Failed to read /proc/net/route: {error}");
+            return vec![];
+        }
+    };
+
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let interface = fields.first()?.to_string();
+            let destination = parse_ipv4(fields.get(1)?)?;
+            let gateway = parse_ipv4(fields.get(2)?)?;
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let metric = fields.get(6)?.parse().ok()?;
+            let prefix_length = parse_ipv4(fields.get(7)?)?
+                .octets()
+                .iter()
+                .map(|octet| octet.count_ones() as u8)
+                .sum();
+
+            Some(Route {
+                family: "ipv4".into(),
+                destination: destination.to_string(),
+                prefix_length,
+                gateway: (flags & RTF_GATEWAY != 0).then(|| gateway.to_string()),
+                interface,
+                metric,
+                is_default: destination.is_unspecified()
+                    && prefix_length == 0
+                    && flags & RTF_REJECT == 0,
+                flags: flags_from(flags),
+            })
+        })
+        .collect()
+}
+
+// Format: destination prefix_length source source_prefix_length next_hop metric refcnt use flags iface
+fn ipv6_routes() -> Vec<Route> {
+    // Missing when IPv6 is disabled
+    let Ok(content) = std::fs::read_to_string("/proc/net/ipv6_route") else {
+        return vec![];
+    };
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let parse_ipv6 = |value: &str| u128::from_str_radix(value, 16).ok().map(Ipv6Addr::from);
+            let destination = parse_ipv6(fields.first()?)?;
+            let prefix_length = u8::from_str_radix(fields.get(1)?, 16).ok()?;
+            let next_hop = parse_ipv6(fields.get(4)?)?;
+            let metric = u32::from_str_radix(fields.get(5)?, 16).ok()?;
+            let flags = u32::from_str_radix(fields.get(8)?, 16).ok()?;
+            let interface = fields.get(9)?.to_string();
+
+            Some(Route {
+                family: "ipv6".into(),
+                destination: destination.to_string(),
+                prefix_length,
+                gateway: (!next_hop.is_unspecified()).then(|| next_hop.to_string()),
+                interface,
+                metric,
+                is_default: destination.is_unspecified()
+                    && prefix_length == 0
+                    && flags & RTF_REJECT == 0,
+                flags: flags_from(flags),
+            })
+        })
+        .collect()
+}
+
+#[cached(time = 5)]
+pub fn routes() -> Vec<Route> {
+    let mut routes = ipv4_routes();
+    routes.extend(ipv6_routes());
+    routes
+}
@@ -0,0 +1,185 @@
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(10);
+// Window used to estimate the discharge rate, longer windows smooth out load spikes
+const HISTORY_WINDOW: Duration = Duration::from_secs(10 * 60);
+// Minimum history required before providing an estimate
+const MINIMUM_HISTORY: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct PowerSupply {
+    name: String,
+    #[serde(rename = "type")]
+    supply_type: String,
+    online: Option<bool>,
+    status: Option<String>,
+    capacity_percent: Option<u8>,
+    voltage_V: Option<f32>,
+    current_A: Option<f32>,
+    power_W: Option<f32>,
+    energy_now_Wh: Option<f32>,
+    energy_full_Wh: Option<f32>,
+    energy_full_design_Wh: Option<f32>,
+    charge_now_Ah: Option<f32>,
+    charge_full_Ah: Option<f32>,
+    charge_full_design_Ah: Option<f32>,
+    health: Option<String>,
+    cycle_count: Option<u64>,
+    temperature: Option<f32>,
+    // Estimated from the discharge rate over the last minutes of history
+    time_to_empty_s: Option<u64>,
+}
+
+struct PowerService {
+    supplies: Vec<PowerSupply>,
+    #[allow(dead_code)]
+    main_loop_thread: thread::JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref POWER_SERVICE: Arc<Mutex<PowerService>> = Arc::new(Mutex::new(PowerService {
+        supplies: vec![],
+        main_loop_thread: thread::spawn(run_main_loop),
+    }));
+}
+
+// Start sampling before the first request, estimates need some history to be available
+pub fn start() {
+    lazy_static::initialize(&POWER_SERVICE);
+}
+
+pub fn power() -> Vec<PowerSupply> {
+    POWER_SERVICE.lock().unwrap().supplies.clone()
+}
+
+fn read_string(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+fn read_number<T: std::str::FromStr>(path: &Path, file: &str) -> Option<T> {
+    read_string(path, file).and_then(|content| content.parse().ok())
+}
+
+// Values are reported in micro units: µV, µA, µW, µWh and µAh
+fn read_micro(path: &Path, file: &str) -> Option<f32> {
+    read_number::<i64>(path, file).map(|value| value as f32 / 1e6)
+}
+
+impl PowerSupply {
+    fn from(path: &Path) -> Self {
+        let voltage_V = read_micro(path, "voltage_now");
+        // Some drivers report a negative current while discharging
+        let current_A = read_micro(path, "current_now").map(f32::abs);
+        let power_W = read_micro(path, "power_now")
+            .map(f32::abs)
+            .or_else(|| Some(voltage_V? * current_A?));
+
+        PowerSupply {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            supply_type: read_string(path, "type").unwrap_or_default(),
+            online: read_string(path, "online").map(|online| online == "1"),
+            status: read_string(path, "status"),
+            capacity_percent: read_number(path, "capacity"),
+            voltage_V,
+            current_A,
+            power_W,
+            energy_now_Wh: read_micro(path, "energy_now"),
+            energy_full_Wh: read_micro(path, "energy_full"),
+            energy_full_design_Wh: read_micro(path, "energy_full_design"),
+            charge_now_Ah: read_micro(path, "charge_now"),
+            charge_full_Ah: read_micro(path, "charge_full"),
+            charge_full_design_Ah: read_micro(path, "charge_full_design"),
+            health: read_string(path, "health"),
+            cycle_count: read_number(path, "cycle_count"),
+            // Reported in tenths of degree Celsius
+            temperature: read_number::<i64>(path, "temp").map(|temp| temp as f32 / 10.0),
+            time_to_empty_s: None,
+        }
+    }
+
+    fn is_discharging(&self) -> bool {
+        self.status.as_deref() == Some("Discharging")
+    }
+
+    // Remaining amount in the most precise unit available
+    fn remaining(&self) -> Option<f32> {
+        self.energy_now_Wh
+            .or(self.charge_now_Ah)
+            .or(self.capacity_percent.map(f32::from))
+    }
+}
+
+fn power_supply_paths() -> Vec<PathBuf> {
+    let mut paths = fs::read_dir(POWER_SUPPLY_PATH)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+// Linear estimate from the oldest and newest samples taken while discharging
+fn time_to_empty(history: &VecDeque<(Instant, f32)>) -> Option<u64> {
+    let (oldest_time, oldest) = history.front()?;
+    let (newest_time, newest) = history.back()?;
+    let elapsed = newest_time.duration_since(*oldest_time);
+    if elapsed < MINIMUM_HISTORY {
+        return None;
+    }
+
+    let rate_per_second = (oldest - newest) / elapsed.as_secs_f32();
+    if rate_per_second <= 0.0 {
+        return None;
+    }
+    Some((newest / rate_per_second) as u64)
+}
+
+fn run_main_loop() {
+    let mut histories: HashMap<String, VecDeque<(Instant, f32)>> = HashMap::new();
+    loop {
+        let now = Instant::now();
+        let mut supplies = power_supply_paths()
+            .iter()
+            .map(|path| PowerSupply::from(path))
+            .collect::<Vec<PowerSupply>>();
+
+        for supply in &mut supplies {
+            let history = histories.entry(supply.name.clone()).or_default();
+            // Charging or a full battery invalidates the discharge history
+            let Some(remaining) = supply.remaining().filter(|_| supply.is_discharging()) else {
+                history.clear();
+                continue;
+            };
+
+            history.push_back((now, remaining));
+            while history.front().map_or(false, |(time, _)| {
+                now.duration_since(*time) > HISTORY_WINDOW
+            }) {
+                history.pop_front();
+            }
+            supply.time_to_empty_s = time_to_empty(history);
+        }
+        histories.retain(|name, _| supplies.iter().any(|supply| &supply.name == name));
+
+        POWER_SERVICE.lock().unwrap().supplies = supplies;
+        thread::sleep(SAMPLING_INTERVAL);
+    }
+}
@@ -0,0 +1,178 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Sensor {
+    channel: String,
+    #[serde(rename = "type")]
+    sensor_type: String,
+    label: Option<String>,
+    unit: String,
+    value: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    critical_min: Option<f64>,
+    critical: Option<f64>,
+    alarm: Option<bool>,
+    fault: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct HwmonDevice {
+    name: String,
+    hwmon: String,
+    device: Option<String>,
+    sensors: Vec<Sensor>,
+}
+
+// hwmon channel prefixes with their type, unit and the divisor of the raw sysfs values
+// See: https://www.kernel.org/doc/html/latest/hwmon/sysfs-interface.html
+const CHANNEL_TYPES: [(&str, &str, &str, f64); 7] = [
+    ("in", "voltage", "V", 1e3),
+    ("curr", "current", "A", 1e3),
+    ("power", "power", "W", 1e6),
+    ("energy", "energy", "J", 1e6),
+    ("fan", "fan", "RPM", 1.0),
+    ("temp", "temperature", "°C", 1e3),
+    ("humidity", "humidity", "%", 1e3),
+];
+
+fn read_string(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+// Splits attribute names like fan1_input into their channel (fan1) and prefix (fan)
+fn channel_from_attribute(attribute: &str) -> Option<(String, &'static str)> {
+    let (channel, _) = attribute.split_once('_')?;
+    CHANNEL_TYPES.iter().find_map(|(prefix, ..)| {
+        let index = channel.strip_prefix(prefix)?;
+        if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some((channel.to_string(), *prefix))
+    })
+}
+
+impl Sensor {
+    fn from(path: &Path, channel: &str, prefix: &str) -> Option<Self> {
+        let (_, sensor_type, unit, divisor) = CHANNEL_TYPES
+            .iter()
+            .find(|(channel_prefix, ..)| *channel_prefix == prefix)?;
+        let read_value = |attribute: &str| {
+            read_string(path, &format!("{channel}_{attribute}"))?
+                .parse::<f64>()
+                .ok()
+                .map(|value| value / divisor)
+        };
+        let read_flags = |attributes: &[&str]| {
+            let flags = attributes
+                .iter()
+                .filter_map(|attribute| read_string(path, &format!("{channel}_{attribute}")))
+                .map(|flag| flag == "1")
+                .collect::<Vec<bool>>();
+            if flags.is_empty() {
+                return None;
+            }
+            Some(flags.into_iter().any(|flag| flag))
+        };
+
+        // Some power meters only provide an average instead of an instantaneous value
+        let value = read_value("input").or_else(|| read_value("average"));
+        let alarm = read_flags(&[
+            "alarm",
+            "min_alarm",
+            "max_alarm",
+            "lcrit_alarm",
+            "crit_alarm",
+        ]);
+
+        Some(Sensor {
+            channel: channel.into(),
+            sensor_type: sensor_type.to_string(),
+            label: read_string(path, &format!("{channel}_label")),
+            unit: unit.to_string(),
+            value,
+            min: read_value("min"),
+            max: read_value("max"),
+            critical_min: read_value("lcrit"),
+            critical: read_value("crit"),
+            alarm,
+            fault: read_flags(&["fault"]),
+        })
+    }
+}
+
+impl HwmonDevice {
+    fn from(path: &Path) -> Self {
+        // Older drivers expose their attributes in the device directory instead
+        let attributes_path = if path.join("name").exists() {
+            path.to_path_buf()
+        } else {
+            path.join("device")
+        };
+
+        let channels = fs::read_dir(&attributes_path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        channel_from_attribute(&entry.file_name().to_string_lossy())
+                    })
+                    .collect::<BTreeSet<(String, &str)>>()
+            })
+            .unwrap_or_default();
+
+        let mut sensors = channels
+            .iter()
+            .filter_map(|(channel, prefix)| Sensor::from(&attributes_path, channel, prefix))
+            .filter(|sensor| sensor.value.is_some())
+            .collect::<Vec<Sensor>>();
+        // Sort channels naturally, e.g: in2 before in10
+        sensors.sort_by_key(|sensor| {
+            let index = sensor
+                .channel
+                .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+                .parse::<u32>()
+                .unwrap_or_default();
+            (sensor.sensor_type.clone(), index)
+        });
+
+        HwmonDevice {
+            name: read_string(&attributes_path, "name").unwrap_or_default(),
+            hwmon: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            device: fs::read_link(path.join("device")).ok().and_then(|device| {
+                device
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            }),
+            sensors,
+        }
+    }
+}
+
+#[cached(time = 5)]
+pub fn sensors() -> Vec<HwmonDevice> {
+    let mut paths = fs::read_dir(HWMON_PATH)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    paths.iter().map(|path| HwmonDevice::from(path)).collect()
+}
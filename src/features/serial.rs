@@ -1,9 +1,22 @@
 use cached::proc_macro::cached;
+use futures::channel::mpsc::{channel, Receiver, Sender};
 use paperclip::actix::Apiv2Schema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serialport::TTYPort;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tracing::*;
 
+// Reads block up to this timeout, so the reader thread notices when the session is closed
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const SEND_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
 #[derive(Clone, Debug, Serialize, Apiv2Schema)]
 pub struct UsbPortInfo {
     /// Vendor ID
@@ -38,6 +51,10 @@ pub struct PortInfo {
     pub by_path: Option<String>,
     /// Time when by_path was created in ms ago
     pub by_path_created_ms_ago: Option<u128>,
+    /// Stable name of the serial port, based on the device serial number
+    pub by_id: Option<String>,
+    /// USB device information, when connected over USB
+    pub usb: Option<UsbPortInfo>,
     /// Udev properties from the device
     pub udev_properties: Option<serde_json::Value>,
 }
@@ -61,14 +78,13 @@ impl PortInfo {
         None
     }
 
-    fn fetch_by_path(device_path: &String) -> (Option<String>, Option<u128>) {
+    fn fetch_link(link_dir: &str, device_path: &String) -> (Option<String>, Option<u128>) {
         let mut sym_path = None;
         let mut time_ago_ms = None;
-        let by_path_dir = "/dev/serial/by-path";
-        let dir = std::fs::read_dir(by_path_dir);
+        let dir = std::fs::read_dir(link_dir);
         if dir.is_err() {
             let error = dir.err().unwrap();
-            warn!("Failed to look over {by_path_dir}: {error:#?}");
+            warn!("Failed to look over {link_dir}: {error:#?}");
             return (None, None);
         }
 
@@ -76,7 +92,7 @@ impl PortInfo {
             sym_path = None;
 
             if let Err(error) = entry {
-                warn!("Failed to open serial by-path folder: {error:#?}");
+                warn!("Failed to open serial {link_dir} folder: {error:#?}");
                 continue;
             }
 
@@ -121,12 +137,22 @@ impl PortInfo {
     }
 
     fn from(port: &serialport::SerialPortInfo, include_udev: bool) -> Self {
-        let (sym_path, time_ago_ms) = PortInfo::fetch_by_path(&port.port_name.clone());
+        let (sym_path, time_ago_ms) =
+            PortInfo::fetch_link("/dev/serial/by-path", &port.port_name.clone());
+        // udev only creates by-id links for USB devices
+        let (by_id, _) = PortInfo::fetch_link("/dev/serial/by-id", &port.port_name.clone());
 
         PortInfo {
             name: port.port_name.clone(),
             by_path: sym_path,
             by_path_created_ms_ago: time_ago_ms,
+            by_id,
+            usb: match &port.port_type {
+                serialport::SerialPortType::UsbPort(usb_port_info) => {
+                    Some(UsbPortInfo::from(usb_port_info))
+                }
+                _ => None,
+            },
             udev_properties: if include_udev {
                 PortInfo::fetch_udev(&port)
             } else {
@@ -141,6 +167,12 @@ pub struct SerialPorts {
     ports: Vec<PortInfo>,
 }
 
+// Hotplug events make the cached ports list stale
+pub fn clear_cache() {
+    use cached::Cached;
+    SERIAL.lock().unwrap().cache_clear();
+}
+
 #[cached(time = 5)]
 pub fn serial(udev: Option<bool>) -> SerialPorts {
     SerialPorts {
@@ -151,3 +183,428 @@ pub fn serial(udev: Option<bool>) -> SerialPorts {
             .collect(),
     }
 }
+
+#[derive(Clone, Debug, Deserialize, Apiv2Schema)]
+pub struct SerialSettings {
+    /// Defaults to 115200
+    baud_rate: Option<u32>,
+    /// 5, 6, 7 or 8, defaults to 8
+    data_bits: Option<u8>,
+    /// none, odd or even, defaults to none
+    parity: Option<String>,
+    /// 1 or 2, defaults to 1
+    stop_bits: Option<u8>,
+    /// none, software or hardware, defaults to none
+    flow_control: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct SerialSession {
+    port: String,
+    baud_rate: u32,
+    data_bits: u8,
+    parity: String,
+    stop_bits: u8,
+    flow_control: String,
+    client: Option<String>,
+    started: String,
+    received_B: u64,
+    sent_B: u64,
+}
+
+#[derive(Debug)]
+pub enum SerialError {
+    NotFound(String),
+    Invalid(String),
+    Busy(String),
+    Io(String),
+}
+
+impl std::fmt::Display for SerialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerialError::NotFound(message)
+            | SerialError::Invalid(message)
+            | SerialError::Busy(message)
+            | SerialError::Io(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+lazy_static! {
+    // Sessions by canonical device path, a port can only be bridged once at a time
+    static ref SESSIONS: Arc<Mutex<HashMap<String, SerialSession>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+pub fn sessions() -> Vec<SerialSession> {
+    let mut sessions: Vec<SerialSession> = SESSIONS.lock().unwrap().values().cloned().collect();
+    sessions.sort_by(|a, b| a.port.cmp(&b.port));
+    sessions
+}
+
+fn update_session(path: &str, update: impl FnOnce(&mut SerialSession)) {
+    if let Some(session) = SESSIONS.lock().unwrap().get_mut(path) {
+        update(session);
+    }
+}
+
+// Ports are relative to /dev, e.g: ttyUSB0, ttyAMA0 or serial/by-id/usb-FTDI_FT232R-if00-port0
+// Only serial ports are accepted, opening other devices has side effects, e.g: /dev/watchdog reboots
+fn device_path(port: &str) -> Result<String, SerialError> {
+    let not_found = || SerialError::NotFound(format!("Serial port not found: {port}"));
+    let path = std::fs::canonicalize(Path::new("/dev").join(port.trim_start_matches('/')))
+        .map_err(|_| not_found())?;
+
+    let is_available = serialport::available_ports()
+        .unwrap_or_default()
+        .iter()
+        .any(|info| std::fs::canonicalize(&info.port_name).map_or(false, |port| port == path));
+    // Hardware ttys have a device in sysfs, unlike virtual consoles and pseudo terminals
+    let is_tty_device = path.parent() == Some(Path::new("/dev"))
+        && path.file_name().map_or(false, |name| {
+            Path::new("/sys/class/tty")
+                .join(name)
+                .join("device")
+                .exists()
+        });
+    if !is_available && !is_tty_device {
+        return Err(not_found());
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+impl SerialSettings {
+    fn to_session(
+        &self,
+        port: String,
+        client: Option<String>,
+    ) -> Result<SerialSession, SerialError> {
+        let parity = self.parity.clone().unwrap_or_else(|| "none".into());
+        let flow_control = self.flow_control.clone().unwrap_or_else(|| "none".into());
+        let session = SerialSession {
+            port,
+            baud_rate: self.baud_rate.unwrap_or(115200),
+            data_bits: self.data_bits.unwrap_or(8),
+            parity: parity.to_lowercase(),
+            stop_bits: self.stop_bits.unwrap_or(1),
+            flow_control: flow_control.to_lowercase(),
+            client,
+            started: crate::features::journal::format_timestamp(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_micros() as u64)
+                    .unwrap_or_default(),
+            ),
+            received_B: 0,
+            sent_B: 0,
+        };
+        // Validate the settings before locking the port
+        session.builder()?;
+        Ok(session)
+    }
+}
+
+impl SerialSession {
+    fn builder(&self) -> Result<serialport::SerialPortBuilder, SerialError> {
+        let data_bits = match self.data_bits {
+            5 => serialport::DataBits::Five,
+            6 => serialport::DataBits::Six,
+            7 => serialport::DataBits::Seven,
+            8 => serialport::DataBits::Eight,
+            other => {
+                return Err(SerialError::Invalid(format!(
+                    "Invalid data bits: {other}, should be 5, 6, 7 or 8"
+                )))
+            }
+        };
+        let parity = match self.parity.as_str() {
+            "none" => serialport::Parity::None,
+            "odd" => serialport::Parity::Odd,
+            "even" => serialport::Parity::Even,
+            other => {
+                return Err(SerialError::Invalid(format!(
+                    "Invalid parity: {other}, should be none, odd or even"
+                )))
+            }
+        };
+        let stop_bits = match self.stop_bits {
+            1 => serialport::StopBits::One,
+            2 => serialport::StopBits::Two,
+            other => {
+                return Err(SerialError::Invalid(format!(
+                    "Invalid stop bits: {other}, should be 1 or 2"
+                )))
+            }
+        };
+        let flow_control = match self.flow_control.as_str() {
+            "none" => serialport::FlowControl::None,
+            "software" => serialport::FlowControl::Software,
+            "hardware" => serialport::FlowControl::Hardware,
+            other => {
+                return Err(SerialError::Invalid(format!(
+                    "Invalid flow control: {other}, should be none, software or hardware"
+                )))
+            }
+        };
+
+        Ok(serialport::new(&self.port, self.baud_rate)
+            .data_bits(data_bits)
+            .parity(parity)
+            .stop_bits(stop_bits)
+            .flow_control(flow_control)
+            .timeout(READ_TIMEOUT))
+    }
+}
+
+// An open serial port registered as an active session until dropped
+pub struct SerialBridge {
+    path: String,
+    port: Option<TTYPort>,
+    reader: Option<thread::JoinHandle<()>>,
+    stop_reader: Arc<AtomicBool>,
+}
+
+impl SerialBridge {
+    pub fn open(
+        port: &str,
+        settings: &SerialSettings,
+        client: Option<String>,
+    ) -> Result<Self, SerialError> {
+        SerialBridge::open_path(device_path(port)?, settings, client)
+    }
+
+    fn open_path(
+        path: String,
+        settings: &SerialSettings,
+        client: Option<String>,
+    ) -> Result<Self, SerialError> {
+        let session = settings.to_session(path.clone(), client)?;
+
+        // Keep the sessions locked while opening to avoid racing for the same port
+        let mut sessions = SESSIONS.lock().unwrap();
+        if sessions.contains_key(&path) {
+            return Err(SerialError::Busy(format!(
+                "Serial port {path} is already in use by another session"
+            )));
+        }
+
+        // Ports are opened in exclusive mode (TIOCEXCL), other processes can't open them either
+        let port = session
+            .builder()?
+            .open_native()
+            .map_err(|error| SerialError::Io(format!("Failed to open {path}: {error}")))?;
+
+        info!("Serial session started: {session:?}");
+        sessions.insert(path.clone(), session);
+        Ok(SerialBridge {
+            path,
+            port: Some(port),
+            reader: None,
+            stop_reader: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        let path = &self.path;
+        self.port
+            .as_mut()
+            .ok_or_else(|| format!("{path} is closed"))?
+            .write_all(data)
+            .map_err(|error| format!("Failed to write to {path}: {error}"))?;
+        update_session(path, |session| session.sent_B += data.len() as u64);
+        Ok(())
+    }
+
+    // Data read from the port, the channel is closed when the port fails, e.g: device unplugged
+    pub fn ask_for_data(&mut self) -> Result<Receiver<Vec<u8>>, String> {
+        if self.reader.is_some() {
+            return Err(format!("{} is already being read", self.path));
+        }
+        let port = self
+            .port
+            .as_ref()
+            .ok_or_else(|| format!("{} is closed", self.path))?
+            .try_clone_native()
+            .map_err(|error| format!("Failed to clone {}: {error}", self.path))?;
+        let (sender, receiver) = channel(1024);
+        let path = self.path.clone();
+        let stop = self.stop_reader.clone();
+        self.reader = Some(thread::spawn(move || read_loop(path, port, sender, stop)));
+        Ok(receiver)
+    }
+}
+
+impl Drop for SerialBridge {
+    // The reader and its cloned port are gone before the session ends, so the port can be opened again right away
+    fn drop(&mut self) {
+        self.stop_reader.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        self.port.take();
+
+        if let Some(session) = SESSIONS.lock().unwrap().remove(&self.path) {
+            info!("Serial session finished: {session:?}");
+        }
+    }
+}
+
+// Waits while the channel is full, but gives up as soon as the bridge stops the reader
+fn send(sender: &mut Sender<Vec<u8>>, mut data: Vec<u8>, stop: &AtomicBool) -> bool {
+    loop {
+        match sender.try_send(data) {
+            Ok(()) => return true,
+            Err(error) if error.is_full() && !stop.load(Ordering::Relaxed) => {
+                data = error.into_inner();
+                thread::sleep(SEND_RETRY_INTERVAL);
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+fn read_loop(path: String, mut port: TTYPort, mut sender: Sender<Vec<u8>>, stop: Arc<AtomicBool>) {
+    let mut buffer = [0; 4096];
+    while !stop.load(Ordering::Relaxed) && !sender.is_closed() {
+        match port.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(size) => {
+                update_session(&path, |session| session.received_B += size as u64);
+                if !send(&mut sender, buffer[..size].to_vec(), &stop) {
+                    break;
+                }
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(error) => {
+                warn!("Failed to read from {path}: {error}");
+                break;
+            }
+        }
+    }
+    debug!("Serial reader finished for {path}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::os::unix::io::FromRawFd;
+
+    fn settings() -> SerialSettings {
+        SerialSettings {
+            baud_rate: None,
+            data_bits: None,
+            parity: None,
+            stop_bits: None,
+            flow_control: None,
+        }
+    }
+
+    // Provides the pseudo terminal master and the slave path, which is bridged as a serial port
+    fn pty() -> (File, String) {
+        let (mut master, mut slave) = (0, 0);
+        let mut name = [0 as libc::c_char; 64];
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(result, 0, "{}", std::io::Error::last_os_error());
+        unsafe { libc::close(slave) };
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .to_string();
+        (unsafe { File::from_raw_fd(master) }, path)
+    }
+
+    fn session(path: &str) -> Option<SerialSession> {
+        sessions().into_iter().find(|session| session.port == path)
+    }
+
+    #[test]
+    fn rejects_devices_other_than_serial_ports() {
+        for port in ["null", "../etc/passwd", "zero", "pts/0", "tty", "missing"] {
+            assert!(
+                matches!(device_path(port), Err(SerialError::NotFound(_))),
+                "{}",
+                port
+            );
+        }
+    }
+
+    #[test]
+    fn bridges_data_both_ways() {
+        let (mut master, path) = pty();
+        let mut bridge = SerialBridge::open_path(path.clone(), &settings(), None).unwrap();
+        let mut receiver = bridge.ask_for_data().unwrap();
+
+        master.write_all(b"ping").unwrap();
+        let mut received = vec![];
+        while received.len() < 4 {
+            received.extend(futures::executor::block_on(receiver.next()).unwrap());
+        }
+        assert_eq!(received, b"ping");
+
+        bridge.write(b"pong").unwrap();
+        let mut sent = [0; 4];
+        master.read_exact(&mut sent).unwrap();
+        assert_eq!(&sent, b"pong");
+
+        let session = session(&path).unwrap();
+        assert_eq!(session.received_B, 4);
+        assert_eq!(session.sent_B, 4);
+    }
+
+    #[test]
+    fn locks_port_while_bridged() {
+        let (_master, path) = pty();
+        let bridge = SerialBridge::open_path(path.clone(), &settings(), None).unwrap();
+
+        assert!(matches!(
+            SerialBridge::open_path(path.clone(), &settings(), None),
+            Err(SerialError::Busy(_))
+        ));
+        // Root is allowed to open ports in exclusive mode
+        if unsafe { libc::geteuid() } != 0 {
+            assert_eq!(
+                std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&path)
+                    .unwrap_err()
+                    .raw_os_error(),
+                Some(libc::EBUSY)
+            );
+        }
+        drop(bridge);
+    }
+
+    #[test]
+    fn ends_session_when_dropped() {
+        let (_master, path) = pty();
+        let mut bridge =
+            SerialBridge::open_path(path.clone(), &settings(), Some("test".into())).unwrap();
+        let mut receiver = bridge.ask_for_data().unwrap();
+        assert_eq!(session(&path).unwrap().client.as_deref(), Some("test"));
+
+        drop(bridge);
+        assert!(session(&path).is_none());
+        // The reader is already finished and the port can be bridged again right away
+        assert!(futures::executor::block_on(receiver.next()).is_none());
+        let bridge = SerialBridge::open_path(path.clone(), &settings(), None).unwrap();
+        assert!(session(&path).is_some());
+        drop(bridge);
+        assert!(session(&path).is_none());
+    }
+}
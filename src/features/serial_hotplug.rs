@@ -0,0 +1,281 @@
+use crate::features::{journal, serial};
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::*;
+
+// Recent events sent to new clients
+const HISTORY_SIZE: usize = 50;
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct SerialEvent {
+    /// add, remove, change, bind or unbind
+    action: String,
+    port: Option<String>,
+    by_path: Option<String>,
+    by_id: Option<String>,
+    usb: Option<serial::UsbPortInfo>,
+    time: String,
+}
+
+struct SerialHotplugService {
+    events: VecDeque<SerialEvent>,
+    senders: Vec<Sender<String>>,
+    #[allow(dead_code)]
+    main_loop_thread: thread::JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref SERIAL_HOTPLUG_SERVICE: Arc<Mutex<SerialHotplugService>> =
+        Arc::new(Mutex::new(SerialHotplugService {
+            events: VecDeque::new(),
+            senders: vec![],
+            main_loop_thread: thread::spawn(run_main_loop),
+        }));
+}
+
+pub fn start() {
+    lazy_static::initialize(&SERIAL_HOTPLUG_SERVICE);
+}
+
+// Starts with the recent events, then provides each new event
+pub fn ask_for_client() -> Receiver<String> {
+    new_client(true)
+}
+
+// Only provides events happening from now on, e.g: to publish each event once
+pub fn ask_for_new_events_client() -> Receiver<String> {
+    new_client(false)
+}
+
+fn new_client(with_history: bool) -> Receiver<String> {
+    let (mut sender, receiver) = channel(1024);
+
+    let mut service = SERIAL_HOTPLUG_SERVICE.lock().unwrap();
+    if with_history {
+        let _ = sender.try_send(serde_json::json!(&service.events).to_string());
+    }
+    service.senders.push(sender);
+
+    receiver
+}
+
+fn add_event(event: SerialEvent) {
+    info!("Serial hotplug event: {event:?}");
+    serial::clear_cache();
+
+    let message = serde_json::json!([&event]).to_string();
+    let mut service = SERIAL_HOTPLUG_SERVICE.lock().unwrap();
+    if service.events.len() == HISTORY_SIZE {
+        service.events.pop_front();
+    }
+    service.events.push_back(event);
+
+    // Clients not keeping up are dropped instead of blocking the udev monitor
+    service
+        .senders
+        .retain_mut(|sender| match sender.try_send(message.clone()) {
+            Ok(()) => true,
+            Err(error) => {
+                if error.is_full() {
+                    warn!("Dropping serial hotplug client, it is not reading events");
+                }
+                false
+            }
+        });
+}
+
+fn property(device: &udev::Device, name: &str) -> Option<String> {
+    device
+        .property_value(name)
+        .map(|value| value.to_string_lossy().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// USB properties are provided by the usb_id builtin, e.g: ID_VENDOR_ID=0403 ID_MODEL_ID=6001
+fn usb_port_info(property: &dyn Fn(&str) -> Option<String>) -> Option<serial::UsbPortInfo> {
+    if property("ID_BUS")? != "usb" {
+        return None;
+    }
+    Some(serial::UsbPortInfo {
+        vid: u16::from_str_radix(&property("ID_VENDOR_ID")?, 16).ok()?,
+        pid: u16::from_str_radix(&property("ID_MODEL_ID")?, 16).ok()?,
+        serial_number: property("ID_SERIAL_SHORT"),
+        manufacturer: property("ID_VENDOR_FROM_DATABASE").or_else(|| property("ID_VENDOR")),
+        product: property("ID_MODEL_FROM_DATABASE").or_else(|| property("ID_MODEL")),
+    })
+}
+
+impl SerialEvent {
+    fn from(event: &udev::Event) -> Self {
+        SerialEvent::from_properties(
+            event.event_type().to_string(),
+            event
+                .devnode()
+                .map(|devnode| devnode.to_string_lossy().to_string()),
+            &|name| property(event, name),
+        )
+    }
+
+    fn from_properties(
+        action: String,
+        port: Option<String>,
+        property: &dyn Fn(&str) -> Option<String>,
+    ) -> Self {
+        // Links are also available on remove events, when the device is already gone
+        let links = property("DEVLINKS").unwrap_or_default();
+        let link = |prefix: &str| {
+            links
+                .split_whitespace()
+                .find(|link| link.starts_with(prefix))
+                .map(String::from)
+        };
+
+        SerialEvent {
+            action,
+            port,
+            by_path: link("/dev/serial/by-path/"),
+            by_id: link("/dev/serial/by-id/"),
+            usb: usb_port_info(property),
+            time: journal::format_timestamp(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_micros() as u64)
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+fn monitor() -> Result<(), String> {
+    let mut socket = udev::MonitorBuilder::new()
+        .and_then(|builder| builder.match_subsystem("tty"))
+        .and_then(|builder| builder.listen())
+        .map_err(|error| format!("Failed to monitor udev tty events: {error}"))?;
+
+    loop {
+        // The monitor socket is non-blocking, wait for events
+        let mut poll = libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut poll, 1, -1) } < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(format!("Failed to wait for udev tty events: {error}"));
+        }
+
+        for event in socket.by_ref() {
+            // Virtual terminals and pseudo terminals are not serial ports
+            if event
+                .devpath()
+                .to_string_lossy()
+                .starts_with("/devices/virtual/")
+            {
+                continue;
+            }
+            add_event(SerialEvent::from(&event));
+        }
+    }
+}
+
+fn run_main_loop() {
+    loop {
+        if let Err(error) = monitor() {
+            warn!("{error}");
+        }
+        thread::sleep(RETRY_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::collections::HashMap;
+
+    fn event(properties: &[(&str, &str)]) -> SerialEvent {
+        let properties: HashMap<&str, &str> = properties.iter().copied().collect();
+        SerialEvent::from_properties("add".into(), Some("/dev/ttyUSB0".into()), &|name| {
+            properties.get(name).map(|value| value.to_string())
+        })
+    }
+
+    #[test]
+    fn parses_usb_serial_event() {
+        let event = event(&[
+            (
+                "DEVLINKS",
+                "/dev/serial/by-path/platform-xhci-hcd.0-usb-0:1:1.0-port0 /dev/serial/by-id/usb-FTDI_FT232R_USB_UART_A50285BI-if00-port0",
+            ),
+            ("ID_BUS", "usb"),
+            ("ID_VENDOR_ID", "0403"),
+            ("ID_MODEL_ID", "6001"),
+            ("ID_SERIAL_SHORT", "A50285BI"),
+            ("ID_VENDOR", "FTDI"),
+            ("ID_VENDOR_FROM_DATABASE", "Future Technology Devices International, Ltd"),
+            ("ID_MODEL", "FT232R_USB_UART"),
+        ]);
+
+        assert_eq!(event.action, "add");
+        assert_eq!(event.port.as_deref(), Some("/dev/ttyUSB0"));
+        assert_eq!(
+            event.by_path.as_deref(),
+            Some("/dev/serial/by-path/platform-xhci-hcd.0-usb-0:1:1.0-port0")
+        );
+        assert_eq!(
+            event.by_id.as_deref(),
+            Some("/dev/serial/by-id/usb-FTDI_FT232R_USB_UART_A50285BI-if00-port0")
+        );
+
+        let usb = event.usb.unwrap();
+        assert_eq!(usb.vid, 0x0403);
+        assert_eq!(usb.pid, 0x6001);
+        assert_eq!(usb.serial_number.as_deref(), Some("A50285BI"));
+        assert_eq!(
+            usb.manufacturer.as_deref(),
+            Some("Future Technology Devices International, Ltd")
+        );
+        assert_eq!(usb.product.as_deref(), Some("FT232R_USB_UART"));
+    }
+
+    #[test]
+    fn ignores_non_usb_properties() {
+        let platform = event(&[("DEVLINKS", "/dev/serial0")]);
+        assert!(platform.by_path.is_none());
+        assert!(platform.by_id.is_none());
+        assert!(platform.usb.is_none());
+
+        let pci = event(&[("ID_BUS", "pci"), ("ID_VENDOR_ID", "8086")]);
+        assert!(pci.usb.is_none());
+
+        let invalid = event(&[
+            ("ID_BUS", "usb"),
+            ("ID_VENDOR_ID", "not hex"),
+            ("ID_MODEL_ID", "6001"),
+        ]);
+        assert!(invalid.usb.is_none());
+    }
+
+    #[test]
+    fn drops_clients_not_reading_events() {
+        let client = ask_for_new_events_client();
+        for _ in 0..2000 {
+            add_event(event(&[]));
+        }
+
+        // The client is dropped once its channel is full, which ends the stream
+        let received = futures::executor::block_on(client.collect::<Vec<String>>());
+        assert!(!received.is_empty() && received.len() < 2000);
+        assert!(received[0].starts_with("[{\"action\":\"add\""));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::features::serial_hotplug;
+use actix::{self, Actor, ActorContext, StreamHandler};
+use actix_web_actors::ws;
+use futures::channel::mpsc::Receiver;
+use tracing::*;
+
+pub fn new_websocket() -> WebsocketActor {
+    WebsocketActor {
+        receiver: Some(serial_hotplug::ask_for_client()),
+    }
+}
+
+pub struct WebsocketActor {
+    receiver: Option<Receiver<String>>,
+}
+
+impl Actor for WebsocketActor {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("Starting serial hotplug websocket");
+        ctx.add_stream(self.receiver.take().unwrap());
+    }
+}
+
+impl StreamHandler<String> for WebsocketActor {
+    fn handle(&mut self, data: String, ctx: &mut Self::Context) {
+        ctx.text(data)
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketActor {
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        // Stopping the actor drops the receiver, which is removed on the next event
+        debug!("Finishing serial hotplug websocket");
+        ctx.stop();
+    }
+
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Text(_)) => {
+                ctx.text("{\"error\":\"Websocket does not support inputs.\"}");
+            }
+            Ok(ws::Message::Binary(bin)) => ctx.binary(bin),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::features::serial::{SerialBridge, SerialError, SerialSettings};
+use actix::{self, Actor, ActorContext, StreamHandler};
+use actix_web_actors::ws;
+use futures::channel::mpsc::Receiver;
+use tracing::*;
+
+pub fn new_websocket(
+    port: &str,
+    settings: &SerialSettings,
+    client: Option<String>,
+) -> Result<WebsocketActor, SerialError> {
+    WebsocketActor::new(port, settings, client)
+}
+
+pub struct WebsocketActor {
+    bridge: SerialBridge,
+    receiver: Option<Receiver<Vec<u8>>>,
+}
+
+impl WebsocketActor {
+    pub fn new(
+        port: &str,
+        settings: &SerialSettings,
+        client: Option<String>,
+    ) -> Result<Self, SerialError> {
+        let mut bridge = SerialBridge::open(port, settings, client)?;
+        let receiver = bridge.ask_for_data().map_err(SerialError::Io)?;
+        Ok(Self {
+            bridge,
+            receiver: Some(receiver),
+        })
+    }
+}
+
+impl Actor for WebsocketActor {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("Starting serial {} websocket", self.bridge.path());
+        ctx.add_stream(self.receiver.take().unwrap());
+    }
+}
+
+impl StreamHandler<Vec<u8>> for WebsocketActor {
+    fn handle(&mut self, data: Vec<u8>, ctx: &mut Self::Context) {
+        ctx.binary(data)
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        // The reader stops when the port fails, e.g: device unplugged
+        debug!("Serial {} reader finished", self.bridge.path());
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Away,
+            description: Some("Serial port closed".into()),
+        }));
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketActor {
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        // Stopping the actor drops the bridge, which closes the port and ends the session
+        debug!("Finishing serial {} websocket", self.bridge.path());
+        ctx.stop();
+    }
+
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let data = match msg {
+            Ok(ws::Message::Ping(msg)) => {
+                ctx.pong(&msg);
+                return;
+            }
+            Ok(ws::Message::Binary(bin)) => bin.to_vec(),
+            Ok(ws::Message::Text(text)) => text.as_bytes().to_vec(),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+                return;
+            }
+            _ => return,
+        };
+
+        if let Err(error) = self.bridge.write(&data) {
+            warn!("{error}");
+            ctx.text(format!(
+                "{{\"error\":{}}}",
+                serde_json::Value::String(error)
+            ));
+        }
+    }
+}
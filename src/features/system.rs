@@ -1,4 +1,7 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use sysinfo::CpuExt;
 use sysinfo::CpuRefreshKind;
 use sysinfo::PidExt;
@@ -12,8 +15,27 @@ use sysinfo::{
 };
 use tracing::*;
 
+const CPU_TIMES_SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+const NETWORK_SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+
 lazy_static! {
     static ref SYSTEM: Arc<Mutex<sysSystem>> = Arc::new(Mutex::new(sysSystem::new()));
+    static ref CPU_TIMES_SERVICE: Arc<Mutex<CpuTimesService>> =
+        Arc::new(Mutex::new(CpuTimesService {
+            times: Err("/proc/stat sampler not initialized".to_string()),
+            main_loop_thread: thread::spawn(run_cpu_times_loop),
+        }));
+    static ref NETWORK_RATES_SERVICE: Arc<Mutex<NetworkRatesService>> =
+        Arc::new(Mutex::new(NetworkRatesService {
+            rates: HashMap::new(),
+            main_loop_thread: thread::spawn(run_network_rates_loop),
+        }));
+}
+
+// Start background samplers, rates need at least two samples to be available
+pub fn start() {
+    lazy_static::initialize(&CPU_TIMES_SERVICE);
+    lazy_static::initialize(&NETWORK_RATES_SERVICE);
 }
 
 #[derive(Clone, Debug, Serialize, Apiv2Schema)]
@@ -25,6 +47,47 @@ pub struct Cpu {
     brand: String,
 }
 
+/// Percentage of time spent in each state during the last sampling interval
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CpuTimePercentages {
+    name: String,
+    user: f32,
+    nice: f32,
+    system: f32,
+    idle: f32,
+    iowait: f32,
+    irq: f32,
+    softirq: f32,
+    steal: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CpuTimes {
+    total: CpuTimePercentages,
+    cores: Vec<CpuTimePercentages>,
+    context_switches_per_second: f32,
+    interrupts_per_second: f32,
+    processes_running: u64,
+    processes_blocked: u64,
+    sampling_interval_ms: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ProcStatSample {
+    // Name and jiffies of each cpu line, the aggregated "cpu" line comes first
+    cpus: Vec<(String, Vec<u64>)>,
+    context_switches: u64,
+    interrupts: u64,
+    processes_running: u64,
+    processes_blocked: u64,
+}
+
+struct CpuTimesService {
+    times: Result<CpuTimes, String>,
+    #[allow(dead_code)]
+    main_loop_thread: thread::JoinHandle<()>,
+}
+
 #[derive(Clone, Debug, Serialize, Apiv2Schema)]
 pub struct Disk {
     name: String,
@@ -50,10 +113,96 @@ pub struct MemoryUsage {
     total_kB: u64,
 }
 
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct HugePages {
+    total: u64,
+    free: u64,
+    reserved: u64,
+    surplus: u64,
+    page_size_kB: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CmaUsage {
+    total_kB: u64,
+    free_kB: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Zram {
+    name: String,
+    algorithm: String,
+    disk_size_B: u64,
+    original_data_B: u64,
+    compressed_data_B: u64,
+    memory_used_B: u64,
+    memory_limit_B: u64,
+    memory_used_max_B: u64,
+    same_pages: u64,
+    pages_compacted: u64,
+    compression_ratio: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct MemoryDetails {
+    free_kB: u64,
+    available_kB: u64,
+    buffers_kB: u64,
+    cached_kB: u64,
+    swap_cached_kB: u64,
+    shared_kB: u64,
+    active_kB: u64,
+    inactive_kB: u64,
+    slab_kB: u64,
+    slab_reclaimable_kB: u64,
+    slab_unreclaimable_kB: u64,
+    dirty_kB: u64,
+    writeback_kB: u64,
+    mapped_kB: u64,
+    page_tables_kB: u64,
+    hugepages: HugePages,
+    cma: Option<CmaUsage>,
+    zram: Vec<Zram>,
+}
+
 #[derive(Clone, Debug, Serialize, Apiv2Schema)]
 pub struct Memory {
     ram: MemoryUsage,
     swap: MemoryUsage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detailed: Option<MemoryDetails>,
+}
+
+/// Rates computed over a fixed sampling window, independent of who else is reading the interfaces
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct NetworkRates {
+    received_B_per_second: f32,
+    transmitted_B_per_second: f32,
+    packets_received_per_second: f32,
+    packets_transmitted_per_second: f32,
+    errors_on_received_per_second: f32,
+    errors_on_transmitted_per_second: f32,
+    drops_on_received_per_second: f32,
+    drops_on_transmitted_per_second: f32,
+    sampling_interval_ms: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+struct NetworkStatistics {
+    received_B: u64,
+    transmitted_B: u64,
+    packets_received: u64,
+    packets_transmitted: u64,
+    errors_on_received: u64,
+    errors_on_transmitted: u64,
+    drops_on_received: u64,
+    drops_on_transmitted: u64,
+}
+
+struct NetworkRatesService {
+    rates: HashMap<String, NetworkRates>,
+    #[allow(dead_code)]
+    main_loop_thread: thread::JoinHandle<()>,
 }
 
 #[derive(Clone, Debug, Serialize, Apiv2Schema)]
@@ -67,6 +216,10 @@ pub struct Network {
     is_up: bool,
     is_loopback: bool,
 
+    mtu: Option<u32>,
+    link_speed_Mbps: Option<u32>,
+    duplex: Option<String>,
+
     received_B: u64,
     total_received_B: u64,
 
@@ -84,6 +237,11 @@ pub struct Network {
 
     errors_on_transmitted: u64,
     total_errors_on_transmitted: u64,
+
+    total_drops_on_received: u64,
+    total_drops_on_transmitted: u64,
+
+    rates: Option<NetworkRates>,
 }
 
 //TODO: be consistent between _B, _b and bytes
@@ -138,7 +296,7 @@ pub fn system() -> System {
         cpu: cpu(),
         disk: disk(),
         info: info(),
-        memory: memory(),
+        memory: memory(None),
         network: network(),
         process: process(),
         temperature: temperature(),
@@ -163,6 +321,141 @@ pub fn cpu() -> Vec<Cpu> {
         .collect::<Vec<Cpu>>()
 }
 
+pub fn cpu_times() -> Result<CpuTimes, String> {
+    CPU_TIMES_SERVICE.lock().unwrap().times.clone()
+}
+
+impl CpuTimePercentages {
+    fn from_samples(name: &str, previous: &[u64], current: &[u64]) -> Self {
+        // user, nice, system, idle, iowait, irq, softirq and steal, guest time is already part of user
+        let deltas = (0..8)
+            .map(|index| {
+                let previous = previous.get(index).copied().unwrap_or_default();
+                let current = current.get(index).copied().unwrap_or_default();
+                current.saturating_sub(previous)
+            })
+            .collect::<Vec<u64>>();
+        let total: u64 = deltas.iter().sum();
+        let percentage = |index: usize| {
+            if total == 0 {
+                return 0.0;
+            }
+            100.0 * deltas[index] as f32 / total as f32
+        };
+
+        CpuTimePercentages {
+            name: name.into(),
+            user: percentage(0),
+            nice: percentage(1),
+            system: percentage(2),
+            idle: percentage(3),
+            iowait: percentage(4),
+            irq: percentage(5),
+            softirq: percentage(6),
+            steal: percentage(7),
+        }
+    }
+}
+
+impl CpuTimes {
+    fn from_samples(
+        previous: &ProcStatSample,
+        current: &ProcStatSample,
+        interval: Duration,
+    ) -> Self {
+        let mut percentages = current
+            .cpus
+            .iter()
+            .map(|(name, jiffies)| {
+                // Cores can go offline between samples, only compare the ones present in both
+                let previous_jiffies = previous
+                    .cpus
+                    .iter()
+                    .find(|(previous_name, _)| previous_name == name)
+                    .map(|(_, jiffies)| jiffies.as_slice())
+                    .unwrap_or(jiffies);
+                CpuTimePercentages::from_samples(name, previous_jiffies, jiffies)
+            })
+            .collect::<Vec<CpuTimePercentages>>();
+        let total = if percentages.is_empty() {
+            CpuTimePercentages::from_samples("cpu", &[], &[])
+        } else {
+            percentages.remove(0)
+        };
+
+        let seconds = interval.as_secs_f32();
+        let rate = |previous: u64, current: u64| {
+            if seconds == 0.0 {
+                return 0.0;
+            }
+            current.saturating_sub(previous) as f32 / seconds
+        };
+
+        CpuTimes {
+            total,
+            cores: percentages,
+            context_switches_per_second: rate(previous.context_switches, current.context_switches),
+            interrupts_per_second: rate(previous.interrupts, current.interrupts),
+            processes_running: current.processes_running,
+            processes_blocked: current.processes_blocked,
+            sampling_interval_ms: interval.as_millis() as u64,
+        }
+    }
+}
+
+fn read_proc_stat() -> Result<ProcStatSample, String> {
+    let content = std::fs::read_to_string("/proc/stat")
+        .map_err(|error| format!("Failed to read /proc/stat: {error}"))?;
+
+    let mut sample = ProcStatSample::default();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(key) = fields.next() else {
+            continue;
+        };
+        let mut values = fields.map(|value| value.parse::<u64>().unwrap_or_default());
+
+        match key {
+            name if name.starts_with("cpu") => {
+                sample.cpus.push((name.to_string(), values.collect()));
+            }
+            "ctxt" => sample.context_switches = values.next().unwrap_or_default(),
+            // The first value is the total, followed by the count of each interrupt
+            "intr" => sample.interrupts = values.next().unwrap_or_default(),
+            "procs_running" => sample.processes_running = values.next().unwrap_or_default(),
+            "procs_blocked" => sample.processes_blocked = values.next().unwrap_or_default(),
+            _ => (),
+        }
+    }
+
+    Ok(sample)
+}
+
+fn run_cpu_times_loop() {
+    let mut previous: Option<(ProcStatSample, Instant)> = None;
+    loop {
+        let now = Instant::now();
+        match read_proc_stat() {
+            Ok(sample) => {
+                if let Some((previous_sample, previous_time)) = &previous {
+                    let times = CpuTimes::from_samples(
+                        previous_sample,
+                        &sample,
+                        now.duration_since(*previous_time),
+                    );
+                    CPU_TIMES_SERVICE.lock().unwrap().times = Ok(times);
+                }
+                previous = Some((sample, now));
+            }
+            Err(error) => {
+                warn!("{error}");
+                CPU_TIMES_SERVICE.lock().unwrap().times = Err(error);
+            }
+        }
+        thread::sleep(CPU_TIMES_SAMPLING_INTERVAL);
+    }
+}
+
 #[cached(time = 5)]
 pub fn disk() -> Vec<Disk> {
     let mut system = SYSTEM.lock().unwrap();
@@ -198,7 +491,7 @@ pub fn info() -> OsInfo {
 }
 
 #[cached(time = 5)]
-pub fn memory() -> Memory {
+pub fn memory(detailed: Option<bool>) -> Memory {
     let mut system = SYSTEM.lock().unwrap();
     system.refresh_memory();
 
@@ -211,15 +504,137 @@ pub fn memory() -> Memory {
             used_kB: system.used_swap().div_ceil(1024),
             total_kB: system.total_swap().div_ceil(1024),
         },
+        detailed: if detailed.unwrap_or(false) {
+            memory_details()
+        } else {
+            None
+        },
     }
 }
 
+fn memory_details() -> Option<MemoryDetails> {
+    let content = match std::fs::read_to_string("/proc/meminfo") {
+        Ok(content) => content,
+        Err(error) => {
+            warn!("Failed to read /proc/meminfo: {error}");
+            return None;
+        }
+    };
+
+    // Lines are in the format of "Key:   value kB", with some entries (HugePages_*) being unitless
+    let meminfo: HashMap<&str, u64> = content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let value = value.split_whitespace().next()?.parse::<u64>().ok()?;
+            Some((key.trim(), value))
+        })
+        .collect();
+    let field = |key: &str| meminfo.get(key).copied().unwrap_or_default();
+
+    Some(MemoryDetails {
+        free_kB: field("MemFree"),
+        available_kB: field("MemAvailable"),
+        buffers_kB: field("Buffers"),
+        cached_kB: field("Cached"),
+        swap_cached_kB: field("SwapCached"),
+        shared_kB: field("Shmem"),
+        active_kB: field("Active"),
+        inactive_kB: field("Inactive"),
+        slab_kB: field("Slab"),
+        slab_reclaimable_kB: field("SReclaimable"),
+        slab_unreclaimable_kB: field("SUnreclaim"),
+        dirty_kB: field("Dirty"),
+        writeback_kB: field("Writeback"),
+        mapped_kB: field("Mapped"),
+        page_tables_kB: field("PageTables"),
+        hugepages: HugePages {
+            total: field("HugePages_Total"),
+            free: field("HugePages_Free"),
+            reserved: field("HugePages_Rsvd"),
+            surplus: field("HugePages_Surp"),
+            page_size_kB: field("Hugepagesize"),
+        },
+        // CMA is only available when the kernel is built with CONFIG_CMA, usually ARM boards
+        cma: meminfo.get("CmaTotal").map(|total| CmaUsage {
+            total_kB: *total,
+            free_kB: field("CmaFree"),
+        }),
+        zram: zram(),
+    })
+}
+
+fn zram() -> Vec<Zram> {
+    let Ok(entries) = std::fs::read_dir("/sys/block") else {
+        return vec![];
+    };
+
+    let mut devices = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().starts_with("zram"))
+                .unwrap_or(false)
+        })
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            let read = |file: &str| std::fs::read_to_string(path.join(file)).ok();
+
+            // mm_stat: orig_data_size compr_data_size mem_used_total mem_limit mem_used_max
+            // same_pages pages_compacted [huge_pages] [huge_pages_since]
+            let mm_stat = read("mm_stat")?
+                .split_whitespace()
+                .map(|value| value.parse::<u64>().unwrap_or_default())
+                .collect::<Vec<u64>>();
+            let stat = |index: usize| mm_stat.get(index).copied().unwrap_or_default();
+
+            // The active algorithm is the one in brackets, e.g: "lzo [lz4] zstd"
+            let algorithm = read("comp_algorithm")
+                .and_then(|content| {
+                    content
+                        .split_whitespace()
+                        .find(|algorithm| algorithm.starts_with('['))
+                        .map(|algorithm| algorithm.trim_matches(|c| c == '[' || c == ']').into())
+                })
+                .unwrap_or_default();
+
+            let original_data_B = stat(0);
+            let compressed_data_B = stat(1);
+
+            Some(Zram {
+                name,
+                algorithm,
+                disk_size_B: read("disksize")
+                    .and_then(|content| content.trim().parse().ok())
+                    .unwrap_or_default(),
+                original_data_B,
+                compressed_data_B,
+                memory_used_B: stat(2),
+                memory_limit_B: stat(3),
+                memory_used_max_B: stat(4),
+                same_pages: stat(5),
+                pages_compacted: stat(6),
+                compression_ratio: if compressed_data_B > 0 {
+                    original_data_B as f32 / compressed_data_B as f32
+                } else {
+                    0.0
+                },
+            })
+        })
+        .collect::<Vec<Zram>>();
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
 pub fn network() -> Vec<Network> {
     let mut system = SYSTEM.lock().unwrap();
     system.refresh_networks();
     system.refresh_networks_list();
 
     let pnet_interfaces = pnet::datalink::interfaces();
+    let rates = NETWORK_RATES_SERVICE.lock().unwrap().rates.clone();
 
     system
         .networks()
@@ -241,6 +656,14 @@ pub fn network() -> Vec<Network> {
                 pnet_interface = interface.clone();
             }
 
+            let sys_path = std::path::Path::new("/sys/class/net").join(name);
+            let read = |file: &str| {
+                std::fs::read_to_string(sys_path.join(file))
+                    .ok()
+                    .map(|content| content.trim().to_string())
+            };
+            let statistics = NetworkStatistics::from_sysfs(name);
+
             Network {
                 name: name.into(),
                 description: pnet_interface.description.clone(),
@@ -254,6 +677,14 @@ pub fn network() -> Vec<Network> {
                 is_up: pnet_interface.is_up(),
                 is_loopback: pnet_interface.is_loopback(),
 
+                mtu: read("mtu").and_then(|mtu| mtu.parse().ok()),
+                // Virtual interfaces and links that are down report -1 or fail to read
+                link_speed_Mbps: read("speed")
+                    .and_then(|speed| speed.parse::<i64>().ok())
+                    .filter(|speed| *speed > 0)
+                    .map(|speed| speed as u32),
+                duplex: read("duplex").filter(|duplex| duplex != "unknown"),
+
                 received_B: network.received(),
                 total_received_B: network.total_received(),
 
@@ -271,11 +702,126 @@ pub fn network() -> Vec<Network> {
 
                 errors_on_transmitted: network.errors_on_transmitted(),
                 total_errors_on_transmitted: network.total_errors_on_transmitted(),
+
+                total_drops_on_received: statistics.drops_on_received,
+                total_drops_on_transmitted: statistics.drops_on_transmitted,
+
+                rates: rates.get(name).cloned(),
             }
         })
         .collect::<Vec<Network>>()
 }
 
+impl NetworkStatistics {
+    fn from_sysfs(interface: &str) -> Self {
+        let path = std::path::Path::new("/sys/class/net")
+            .join(interface)
+            .join("statistics");
+        let read = |file: &str| {
+            std::fs::read_to_string(path.join(file))
+                .ok()
+                .and_then(|content| content.trim().parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+
+        NetworkStatistics {
+            received_B: read("rx_bytes"),
+            transmitted_B: read("tx_bytes"),
+            packets_received: read("rx_packets"),
+            packets_transmitted: read("tx_packets"),
+            errors_on_received: read("rx_errors"),
+            errors_on_transmitted: read("tx_errors"),
+            drops_on_received: read("rx_dropped"),
+            drops_on_transmitted: read("tx_dropped"),
+        }
+    }
+}
+
+impl NetworkRates {
+    fn from_samples(
+        previous: &NetworkStatistics,
+        current: &NetworkStatistics,
+        interval: Duration,
+    ) -> Self {
+        let seconds = interval.as_secs_f32();
+        let rate = |previous: u64, current: u64| {
+            if seconds == 0.0 {
+                return 0.0;
+            }
+            current.saturating_sub(previous) as f32 / seconds
+        };
+
+        NetworkRates {
+            received_B_per_second: rate(previous.received_B, current.received_B),
+            transmitted_B_per_second: rate(previous.transmitted_B, current.transmitted_B),
+            packets_received_per_second: rate(previous.packets_received, current.packets_received),
+            packets_transmitted_per_second: rate(
+                previous.packets_transmitted,
+                current.packets_transmitted,
+            ),
+            errors_on_received_per_second: rate(
+                previous.errors_on_received,
+                current.errors_on_received,
+            ),
+            errors_on_transmitted_per_second: rate(
+                previous.errors_on_transmitted,
+                current.errors_on_transmitted,
+            ),
+            drops_on_received_per_second: rate(
+                previous.drops_on_received,
+                current.drops_on_received,
+            ),
+            drops_on_transmitted_per_second: rate(
+                previous.drops_on_transmitted,
+                current.drops_on_transmitted,
+            ),
+            sampling_interval_ms: interval.as_millis() as u64,
+        }
+    }
+}
+
+fn run_network_rates_loop() {
+    let mut previous: Option<(HashMap<String, NetworkStatistics>, Instant)> = None;
+    loop {
+        let now = Instant::now();
+        let interfaces = match std::fs::read_dir("/sys/class/net") {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect::<Vec<String>>(),
+            Err(error) => {
+                warn!("Failed to look over /sys/class/net: {error}");
+                vec![]
+            }
+        };
+        let statistics = interfaces
+            .into_iter()
+            .map(|interface| {
+                let statistics = NetworkStatistics::from_sysfs(&interface);
+                (interface, statistics)
+            })
+            .collect::<HashMap<String, NetworkStatistics>>();
+
+        if let Some((previous_statistics, previous_time)) = &previous {
+            let interval = now.duration_since(*previous_time);
+            let rates = statistics
+                .iter()
+                .filter_map(|(interface, current)| {
+                    let previous = previous_statistics.get(interface)?;
+                    Some((
+                        interface.clone(),
+                        NetworkRates::from_samples(previous, current, interval),
+                    ))
+                })
+                .collect();
+            NETWORK_RATES_SERVICE.lock().unwrap().rates = rates;
+        }
+        previous = Some((statistics, now));
+
+        thread::sleep(NETWORK_SAMPLING_INTERVAL);
+    }
+}
+
 #[cached(time = 5)]
 pub fn process() -> Vec<Process> {
     let mut system = SYSTEM.lock().unwrap();
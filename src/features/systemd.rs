@@ -0,0 +1,510 @@
+use crate::features::journal::{self, JournalEntry};
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::process::Command;
+use tracing::*;
+
+// Properties used for each unit accounting, see: systemctl show --property=...
+const ACCOUNTING_PROPERTIES: &str = "MainPID,MemoryCurrent,CPUUsageNSec,NRestarts";
+// Journal lines provided for each failed unit
+const FAILED_UNIT_JOURNAL_LINES: usize = 10;
+// Unit types systemd accepts, names without one of them default to services
+const UNIT_SUFFIXES: [&str; 11] = [
+    ".service",
+    ".socket",
+    ".device",
+    ".mount",
+    ".automount",
+    ".swap",
+    ".target",
+    ".path",
+    ".timer",
+    ".slice",
+    ".scope",
+];
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Unit {
+    name: String,
+    description: String,
+    load_state: String,
+    active_state: String,
+    sub_state: String,
+    main_pid: Option<u32>,
+    memory_current_B: Option<u64>,
+    cpu_usage_ms: Option<u64>,
+    restart_count: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct UnitDetails {
+    unit: Unit,
+    properties: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct FailedUnit {
+    unit: Unit,
+    result: Option<String>,
+    journal: Vec<JournalEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct FailedUnitsSummary {
+    count: usize,
+    units: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Timer {
+    name: String,
+    activates: Option<String>,
+    last_trigger: Option<String>,
+    next_elapse: Option<String>,
+    // Result of the last run of the activated unit
+    result: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl std::fmt::Display for UnitAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitAction::Start => write!(f, "start"),
+            UnitAction::Stop => write!(f, "stop"),
+            UnitAction::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SystemdError {
+    NotFound(String),
+    Io(String),
+}
+
+impl std::fmt::Display for SystemdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemdError::NotFound(message) | SystemdError::Io(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+fn run(program: &str, arguments: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(arguments)
+        .output()
+        .map_err(|error| format!("Failed to run {program}: {error}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{program} {} failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Appends .service to names without a unit type, as systemctl does
+pub fn unit_name(name: &str) -> String {
+    if UNIT_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+        return name.to_string();
+    }
+    format!("{name}.service")
+}
+
+// Names are compared after adding the default unit type, e.g: "mavlink-router" allows "mavlink-router.service"
+pub fn is_control_allowed(name: &str, allowed_units: &[String]) -> bool {
+    let name = unit_name(name);
+    allowed_units.iter().any(|unit| unit_name(unit) == name)
+}
+
+// systemd reports unset values as [not set] or, on older versions, as the maximum of the type
+fn parse_property<T: std::str::FromStr>(value: &str) -> Option<T> {
+    if value == u64::MAX.to_string() || value == u32::MAX.to_string() {
+        return None;
+    }
+    value.parse().ok()
+}
+
+impl Unit {
+    fn new(name: &str, description: &str, load: &str, active: &str, sub: &str) -> Self {
+        Unit {
+            name: name.into(),
+            description: description.into(),
+            load_state: load.into(),
+            active_state: active.into(),
+            sub_state: sub.into(),
+            main_pid: None,
+            memory_current_B: None,
+            cpu_usage_ms: None,
+            restart_count: None,
+        }
+    }
+
+    fn update_accounting(&mut self, properties: &HashMap<String, String>) {
+        let property = |name: &str| properties.get(name).map(String::as_str).unwrap_or("");
+        // Units without a running main process report 0
+        self.main_pid = parse_property::<u32>(property("MainPID")).filter(|pid| *pid != 0);
+        self.memory_current_B = parse_property(property("MemoryCurrent"));
+        self.cpu_usage_ms = parse_property::<u64>(property("CPUUsageNSec"))
+            .map(|nanoseconds| nanoseconds / 1_000_000);
+        self.restart_count = parse_property(property("NRestarts"));
+    }
+}
+
+// Lists units via D-Bus, ListUnits returns a(ssssssouso):
+// name, description, load state, active state, sub state, followed unit, object path, job id, job type, job path
+fn list_units_dbus() -> Result<Vec<Unit>, String> {
+    let output = run(
+        "busctl",
+        &[
+            "--system",
+            "--json=short",
+            "call",
+            "org.freedesktop.systemd1",
+            "/org/freedesktop/systemd1",
+            "org.freedesktop.systemd1.Manager",
+            "ListUnits",
+        ],
+    )?;
+    parse_units_dbus(&output)
+}
+
+fn parse_units_dbus(output: &str) -> Result<Vec<Unit>, String> {
+    let value: Value = serde_json::from_str(output)
+        .map_err(|error| format!("Invalid busctl json payload: {error}"))?;
+
+    let units = value
+        .get("data")
+        .and_then(|data| data.get(0))
+        .and_then(|units| units.as_array())
+        .ok_or_else(|| "Missing units in ListUnits reply".to_string())?;
+
+    Ok(units
+        .iter()
+        .filter_map(|unit| {
+            let field = |index: usize| unit.get(index).and_then(|value| value.as_str());
+            Some(Unit::new(
+                field(0)?,
+                field(1)?,
+                field(2)?,
+                field(3)?,
+                field(4)?,
+            ))
+        })
+        .collect())
+}
+
+fn list_units_systemctl() -> Result<Vec<Unit>, String> {
+    let output = run("systemctl", &["list-units", "--all", "--output=json"])?;
+    parse_units_systemctl(&output)
+}
+
+fn parse_units_systemctl(output: &str) -> Result<Vec<Unit>, String> {
+    let value: Value = serde_json::from_str(output)
+        .map_err(|error| format!("Invalid systemctl json payload: {error}"))?;
+
+    let units = value
+        .as_array()
+        .ok_or_else(|| "Expected a list of units from systemctl".to_string())?;
+
+    Ok(units
+        .iter()
+        .filter_map(|unit| {
+            let field = |name: &str| unit.get(name).and_then(|value| value.as_str());
+            Some(Unit::new(
+                field("unit")?,
+                field("description").unwrap_or_default(),
+                field("load")?,
+                field("active")?,
+                field("sub")?,
+            ))
+        })
+        .collect())
+}
+
+// Parses systemctl show output, one Key=Value per line and units separated by an empty line
+fn parse_show(output: &str) -> Vec<HashMap<String, String>> {
+    output
+        .split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<String, String>>()
+        })
+        .filter(|properties| !properties.is_empty())
+        .collect()
+}
+
+// Provides the desired properties of each unit, keyed by unit name
+fn show(
+    names: &[&str],
+    properties: &str,
+) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let properties = format!("Id,{properties}");
+    let mut arguments = vec!["show", "--property", &properties, "--"];
+    arguments.extend(names);
+    Ok(parse_show(&run("systemctl", &arguments)?)
+        .into_iter()
+        .filter_map(|properties| Some((properties.get("Id")?.clone(), properties)))
+        .collect())
+}
+
+#[cached(time = 5)]
+pub fn units() -> Result<Vec<Unit>, String> {
+    let mut units = match list_units_dbus() {
+        Ok(units) => units,
+        Err(error) => {
+            debug!("Falling back to systemctl: {error}");
+            list_units_systemctl()?
+        }
+    };
+
+    let names = units
+        .iter()
+        .map(|unit| unit.name.as_str())
+        .collect::<Vec<&str>>();
+    match show(&names, ACCOUNTING_PROPERTIES) {
+        Ok(accounting) => {
+            for unit in &mut units {
+                if let Some(properties) = accounting.get(&unit.name) {
+                    unit.update_accounting(properties);
+                }
+            }
+        }
+        Err(error) => warn!("Failed to get units accounting: {error}"),
+    }
+
+    units.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(units)
+}
+
+pub fn unit(name: &str) -> Result<UnitDetails, SystemdError> {
+    let name = unit_name(name);
+    let output = run("systemctl", &["show", "--", &name]).map_err(SystemdError::Io)?;
+    let properties = parse_show(&output)
+        .into_iter()
+        .next()
+        .ok_or_else(|| SystemdError::NotFound(format!("Unit not found: {name}")))?;
+
+    let property = |key: &str| properties.get(key).map(String::as_str).unwrap_or("");
+    if property("LoadState") == "not-found" {
+        return Err(SystemdError::NotFound(format!("Unit not found: {name}")));
+    }
+
+    let mut unit = Unit::new(
+        property("Id"),
+        property("Description"),
+        property("LoadState"),
+        property("ActiveState"),
+        property("SubState"),
+    );
+    unit.update_accounting(&properties);
+
+    Ok(UnitDetails {
+        unit,
+        properties: properties.into_iter().collect(),
+    })
+}
+
+// Runs the action and waits for the job to finish, allowlist checks are done by the caller
+pub fn control(name: &str, action: &UnitAction) -> Result<UnitDetails, SystemdError> {
+    let name = unit_name(name);
+    // Check the unit exists before queueing a job for it
+    unit(&name)?;
+
+    info!("Running systemctl {action} {name}");
+    run("systemctl", &[&action.to_string(), "--", &name]).map_err(SystemdError::Io)?;
+    unit(&name)
+}
+
+// Failed units with their last journal lines, the journal follower provides lines after it starts
+pub fn failed() -> Result<Vec<FailedUnit>, String> {
+    let units = units()?
+        .into_iter()
+        .filter(|unit| unit.active_state == "failed")
+        .collect::<Vec<Unit>>();
+
+    let names = units
+        .iter()
+        .map(|unit| unit.name.as_str())
+        .collect::<Vec<&str>>();
+    // Units are still listed when their result is unavailable
+    let properties = show(&names, "Result").unwrap_or_else(|error| {
+        warn!("Failed to get failed units result: {error}");
+        HashMap::new()
+    });
+
+    Ok(units
+        .into_iter()
+        .map(|unit| FailedUnit {
+            result: properties
+                .get(&unit.name)
+                .and_then(|properties| properties.get("Result"))
+                .cloned(),
+            journal: journal::unit_entries(&unit.name, FAILED_UNIT_JOURNAL_LINES),
+            unit,
+        })
+        .collect())
+}
+
+pub fn failed_summary() -> Result<FailedUnitsSummary, String> {
+    let units = units()?
+        .into_iter()
+        .filter(|unit| unit.active_state == "failed")
+        .map(|unit| unit.name)
+        .collect::<Vec<String>>();
+    Ok(FailedUnitsSummary {
+        count: units.len(),
+        units,
+    })
+}
+
+// Lists timers with their trigger times in microseconds since epoch, null when not applicable
+// e.g: [{"next":1700000000000000,"left":..,"last":..,"passed":..,"unit":"apt-daily.timer","activates":"apt-daily.service"}]
+#[cached(time = 5)]
+pub fn timers() -> Result<Vec<Timer>, String> {
+    let output = run("systemctl", &["list-timers", "--all", "--output=json"])?;
+    let value: Value = serde_json::from_str(&output)
+        .map_err(|error| format!("Invalid systemctl json payload: {error}"))?;
+
+    let mut timers = value
+        .as_array()
+        .ok_or_else(|| "Expected a list of timers from systemctl".to_string())?
+        .iter()
+        .filter_map(|timer| {
+            let timestamp = |name: &str| {
+                timer
+                    .get(name)
+                    .and_then(|value| value.as_u64())
+                    .filter(|timestamp| *timestamp != 0)
+                    .map(journal::format_timestamp)
+            };
+            Some(Timer {
+                name: timer.get("unit")?.as_str()?.to_string(),
+                activates: timer
+                    .get("activates")
+                    .and_then(|value| value.as_str())
+                    .map(String::from),
+                last_trigger: timestamp("last"),
+                next_elapse: timestamp("next"),
+                result: None,
+            })
+        })
+        .collect::<Vec<Timer>>();
+
+    let activated = timers
+        .iter()
+        .filter_map(|timer| timer.activates.as_deref())
+        .collect::<Vec<&str>>();
+    // Timers are still listed when the result of their units is unavailable
+    let properties = show(&activated, "Result").unwrap_or_else(|error| {
+        warn!("Failed to get timers result: {error}");
+        HashMap::new()
+    });
+    for timer in &mut timers {
+        timer.result = timer
+            .activates
+            .as_ref()
+            .and_then(|name| properties.get(name))
+            .and_then(|properties| properties.get("Result"))
+            .cloned();
+    }
+
+    timers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(timers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_busctl_list_units() {
+        let output = r#"{"type":"a(ssssssouso)","data":[[
+            ["mavlink-router.service","MAVLink Router","loaded","active","running","","/org/freedesktop/systemd1/unit/mavlink_2drouter_2eservice",0,"","/"],
+            ["video.service","Video","loaded","failed","failed","","/org/freedesktop/systemd1/unit/video_2eservice",0,"","/"],
+            ["broken"]
+        ]]}"#;
+
+        let units = parse_units_dbus(output).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].name, "mavlink-router.service");
+        assert_eq!(units[0].description, "MAVLink Router");
+        assert_eq!(units[0].active_state, "active");
+        assert_eq!(units[1].sub_state, "failed");
+        assert!(units[1].main_pid.is_none());
+
+        assert!(parse_units_dbus("not json").is_err());
+        assert!(parse_units_dbus(r#"{"type":"a(ssssssouso)","data":[]}"#).is_err());
+    }
+
+    #[test]
+    fn parses_systemctl_list_units() {
+        let output = r#"[
+            {"unit":"ssh.service","load":"loaded","active":"active","sub":"running","description":"OpenBSD Secure Shell server"},
+            {"unit":"dev-ttyAMA0.device","load":"loaded","active":"active","sub":"plugged"},
+            {"load":"loaded","active":"active","sub":"running"}
+        ]"#;
+
+        let units = parse_units_systemctl(output).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].name, "ssh.service");
+        assert_eq!(units[0].load_state, "loaded");
+        assert_eq!(units[1].name, "dev-ttyAMA0.device");
+        assert_eq!(units[1].description, "");
+
+        assert!(parse_units_systemctl(r#"{"unit":"ssh.service"}"#).is_err());
+    }
+
+    #[test]
+    fn parses_show_accounting() {
+        let output = "Id=ssh.service\nMainPID=612\nMemoryCurrent=18446744073709551615\nCPUUsageNSec=1500000000\nNRestarts=2\n\nId=video.service\nMainPID=0\nMemoryCurrent=[not set]\n";
+
+        let properties = parse_show(output);
+        assert_eq!(properties.len(), 2);
+
+        let mut unit = Unit::new("ssh.service", "", "loaded", "active", "running");
+        unit.update_accounting(&properties[0]);
+        assert_eq!(unit.main_pid, Some(612));
+        assert_eq!(unit.memory_current_B, None);
+        assert_eq!(unit.cpu_usage_ms, Some(1500));
+        assert_eq!(unit.restart_count, Some(2));
+
+        let mut unit = Unit::new("video.service", "", "loaded", "failed", "failed");
+        unit.update_accounting(&properties[1]);
+        assert_eq!(unit.main_pid, None);
+        assert_eq!(unit.memory_current_B, None);
+    }
+
+    #[test]
+    fn allows_only_listed_units() {
+        let allowed_units = vec!["mavlink-router".to_string(), "video.timer".to_string()];
+
+        assert!(is_control_allowed("mavlink-router", &allowed_units));
+        assert!(is_control_allowed("mavlink-router.service", &allowed_units));
+        assert!(is_control_allowed("video.timer", &allowed_units));
+        assert!(!is_control_allowed("video", &allowed_units));
+        assert!(!is_control_allowed("ssh.service", &allowed_units));
+        assert!(!is_control_allowed("mavlink-router.socket", &allowed_units));
+        assert!(!is_control_allowed("mavlink-router", &[]));
+    }
+}
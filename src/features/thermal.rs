@@ -0,0 +1,158 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const THERMAL_PATH: &str = "/sys/class/thermal";
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct TripPoint {
+    #[serde(rename = "type")]
+    trip_type: String,
+    temperature: Option<f32>,
+    hysteresis: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct ThermalZone {
+    name: String,
+    #[serde(rename = "type")]
+    zone_type: String,
+    temperature: Option<f32>,
+    mode: Option<String>,
+    policy: Option<String>,
+    trip_points: Vec<TripPoint>,
+    // Degrees left until the closest trip point above the current temperature
+    next_trip_headroom: Option<f32>,
+    cooling_devices: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct CoolingDevice {
+    name: String,
+    #[serde(rename = "type")]
+    device_type: String,
+    current_state: Option<u64>,
+    max_state: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Thermal {
+    zones: Vec<ThermalZone>,
+    cooling_devices: Vec<CoolingDevice>,
+}
+
+fn read_string(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+fn read_number<T: std::str::FromStr>(path: &Path, file: &str) -> Option<T> {
+    read_string(path, file).and_then(|content| content.parse().ok())
+}
+
+// Temperatures are reported in millidegrees Celsius
+fn read_temperature(path: &Path, file: &str) -> Option<f32> {
+    read_number::<i64>(path, file).map(|temperature| temperature as f32 / 1000.0)
+}
+
+// Lists entries like thermal_zone0, thermal_zone1.. sorted by their index
+fn entries_with_prefix(path: &Path, prefix: &str) -> Vec<(u32, PathBuf)> {
+    let mut entries = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let index = entry
+                        .file_name()
+                        .to_string_lossy()
+                        .strip_prefix(prefix)?
+                        .parse::<u32>()
+                        .ok()?;
+                    Some((index, entry.path()))
+                })
+                .collect::<Vec<(u32, PathBuf)>>()
+        })
+        .unwrap_or_default();
+    entries.sort_by_key(|(index, _)| *index);
+    entries
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+impl ThermalZone {
+    fn from(path: &Path) -> Self {
+        let temperature = read_temperature(path, "temp");
+
+        // Trip points are numbered without gaps: trip_point_0_type, trip_point_0_temp..
+        let trip_points = (0..)
+            .map_while(|index| {
+                Some(TripPoint {
+                    trip_type: read_string(path, &format!("trip_point_{index}_type"))?,
+                    temperature: read_temperature(path, &format!("trip_point_{index}_temp")),
+                    hysteresis: read_temperature(path, &format!("trip_point_{index}_hyst")),
+                })
+            })
+            .collect::<Vec<TripPoint>>();
+
+        let next_trip_headroom = temperature.and_then(|temperature| {
+            trip_points
+                .iter()
+                .filter_map(|trip_point| trip_point.temperature)
+                .filter(|trip_temperature| *trip_temperature > temperature)
+                .map(|trip_temperature| trip_temperature - temperature)
+                .reduce(f32::min)
+        });
+
+        // Bound cooling devices are linked as cdev0, cdev1.. pointing to cooling_deviceN
+        let cooling_devices = entries_with_prefix(path, "cdev")
+            .iter()
+            .filter_map(|(_, link)| fs::read_link(link).ok())
+            .map(|target| name(&target))
+            .collect();
+
+        ThermalZone {
+            name: name(path),
+            zone_type: read_string(path, "type").unwrap_or_default(),
+            temperature,
+            mode: read_string(path, "mode"),
+            policy: read_string(path, "policy"),
+            trip_points,
+            next_trip_headroom,
+            cooling_devices,
+        }
+    }
+}
+
+impl CoolingDevice {
+    fn from(path: &Path) -> Self {
+        CoolingDevice {
+            name: name(path),
+            device_type: read_string(path, "type").unwrap_or_default(),
+            current_state: read_number(path, "cur_state"),
+            max_state: read_number(path, "max_state"),
+        }
+    }
+}
+
+#[cached(time = 5)]
+pub fn thermal() -> Thermal {
+    let path = Path::new(THERMAL_PATH);
+    Thermal {
+        zones: entries_with_prefix(path, "thermal_zone")
+            .iter()
+            .map(|(_, path)| ThermalZone::from(path))
+            .collect(),
+        cooling_devices: entries_with_prefix(path, "cooling_device")
+            .iter()
+            .map(|(_, path)| CoolingDevice::from(path))
+            .collect(),
+    }
+}
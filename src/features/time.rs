@@ -0,0 +1,391 @@
+use crate::features::journal;
+use cached::proc_macro::cached;
+use chrono::DateTime;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::*;
+
+const RTC_PATH: &str = "/sys/class/rtc/rtc0";
+// Accepted time range, anything else is most likely a unit mistake, e.g: milliseconds instead of seconds
+const MIN_TIME_S: i64 = 946_684_800; // 2000-01-01T00:00:00Z
+const MAX_TIME_S: i64 = 4_102_444_800; // 2100-01-01T00:00:00Z
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct KernelClock {
+    is_synchronized: bool,
+    offset_ms: f64,
+    max_error_ms: f64,
+    estimated_error_ms: f64,
+    frequency_ppm: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct NtpStatus {
+    service: String,
+    server: Option<String>,
+    stratum: Option<u32>,
+    offset_ms: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Time {
+    system_time: String,
+    timezone: Option<String>,
+    rtc_time: Option<String>,
+    is_synchronized: bool,
+    kernel: Option<KernelClock>,
+    ntp: Option<NtpStatus>,
+}
+
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct TimeUpdate {
+    // Unix timestamp in seconds, e.g: "1700000000.5", or RFC3339, e.g: "2023-11-14T22:13:20Z"
+    time: String,
+    set_hwclock: Option<bool>,
+    // Allows moving the clock backwards
+    force: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct TimeJump {
+    previous_time: String,
+    time: String,
+    jump_ms: i64,
+    is_hwclock_set: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hwclock_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum TimeError {
+    Invalid(String),
+    Backwards(String),
+    Io(String),
+}
+
+impl std::fmt::Display for TimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeError::Invalid(message)
+            | TimeError::Backwards(message)
+            | TimeError::Io(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+fn run(program: &str, arguments: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(arguments).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// The kernel clock is considered synchronized when an NTP daemon keeps clearing STA_UNSYNC
+pub fn kernel_clock() -> Result<KernelClock, String> {
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        return Err(format!(
+            "adjtimex failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    // Offset is in nanoseconds when STA_NANO is set, microseconds otherwise
+    let offset_divisor = if timex.status & libc::STA_NANO != 0 {
+        1_000_000.0
+    } else {
+        1_000.0
+    };
+
+    Ok(KernelClock {
+        is_synchronized: state != libc::TIME_ERROR && timex.status & libc::STA_UNSYNC == 0,
+        offset_ms: timex.offset as f64 / offset_divisor,
+        max_error_ms: timex.maxerror as f64 / 1_000.0,
+        estimated_error_ms: timex.esterror as f64 / 1_000.0,
+        // Frequency is in ppm with a 16 bits fractional part
+        frequency_ppm: timex.freq as f64 / 65536.0,
+    })
+}
+
+pub fn is_synchronized() -> bool {
+    kernel_clock().map_or(false, |clock| clock.is_synchronized)
+}
+
+// /etc/localtime links to the zone file, e.g: /usr/share/zoneinfo/Europe/Paris
+fn timezone() -> Option<String> {
+    let zone = fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|path| {
+            path.to_string_lossy()
+                .split_once("zoneinfo/")
+                .map(|(_, zone)| zone.to_string())
+        })
+        .or_else(|| {
+            fs::read_to_string("/etc/timezone")
+                .ok()
+                .map(|zone| zone.trim().to_string())
+        })?;
+    Some(zone).filter(|zone| !zone.is_empty())
+}
+
+// The RTC keeps UTC time on Linux, unless configured otherwise with timedatectl set-local-rtc
+fn rtc_time() -> Option<String> {
+    let seconds: u64 = fs::read_to_string(format!("{RTC_PATH}/since_epoch"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(journal::format_timestamp(seconds * 1_000_000))
+}
+
+// Format: comma separated values, e.g:
+// A29FC87B,time.cloudflare.com,4,1700000000.123456,-0.000012345,...,Normal
+// Fields are reference id, reference name, stratum, reference time and system time offset in seconds
+fn chrony() -> Option<NtpStatus> {
+    let output = run("chronyc", &["-c", "tracking"])?;
+    let fields: Vec<&str> = output.trim().split(',').collect();
+    if fields.len() < 5 {
+        return None;
+    }
+
+    Some(NtpStatus {
+        service: "chrony".into(),
+        server: Some(fields[1].to_string()).filter(|server| !server.is_empty()),
+        stratum: fields[2].parse().ok(),
+        offset_ms: fields[4].parse::<f64>().ok().map(|offset| offset * 1_000.0),
+    })
+}
+
+// Converts durations like 12us, -1.225ms or +2s
+fn parse_duration_ms(value: &str) -> Option<f64> {
+    let (number, divisor) = if let Some(number) = value.strip_suffix("us") {
+        (number, 1_000.0)
+    } else if let Some(number) = value.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 0.001)
+    } else {
+        return None;
+    };
+    number
+        .trim_start_matches('+')
+        .parse::<f64>()
+        .ok()
+        .map(|number| number / divisor)
+}
+
+// Format: key: value, one per line, e.g:
+//        Server: 185.125.190.56 (ntp.ubuntu.com)
+//       Stratum: 2
+//        Offset: -1.225ms
+fn timesyncd() -> Option<NtpStatus> {
+    let output = run("timedatectl", &["timesync-status", "--no-pager"])?;
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (line_key, value) = line.split_once(':')?;
+            (line_key.trim() == key).then(|| value.trim().to_string())
+        })
+    };
+
+    Some(NtpStatus {
+        service: "systemd-timesyncd".into(),
+        server: value("Server"),
+        stratum: value("Stratum").and_then(|stratum| stratum.parse().ok()),
+        offset_ms: value("Offset").and_then(|offset| parse_duration_ms(&offset)),
+    })
+}
+
+#[cached(time = 5)]
+fn ntp() -> Option<NtpStatus> {
+    chrony().or_else(timesyncd)
+}
+
+fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+pub fn time() -> Time {
+    let microseconds = now_us();
+
+    let kernel = kernel_clock()
+        .map_err(|error| warn!("Failed to get kernel clock state: {error}"))
+        .ok();
+
+    Time {
+        system_time: journal::format_timestamp(microseconds),
+        timezone: timezone(),
+        rtc_time: rtc_time(),
+        is_synchronized: kernel.as_ref().map_or(false, |clock| clock.is_synchronized),
+        kernel,
+        ntp: ntp(),
+    }
+}
+
+// Compares every byte so the response time does not reveal how much of the token matched
+pub fn is_authorized(token: &str, authorization: Option<&str>) -> bool {
+    let Some(provided) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let (token, provided) = (token.as_bytes(), provided.as_bytes());
+    if token.is_empty() || token.len() != provided.len() {
+        return false;
+    }
+    token
+        .iter()
+        .zip(provided)
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+fn parse_time_us(time: &str) -> Result<u64, TimeError> {
+    let time = time.trim();
+    let out_of_range = || {
+        TimeError::Invalid(format!(
+            "Time should be between 2000-01-01 and 2100-01-01: {time}"
+        ))
+    };
+
+    if let Ok(seconds) = time.parse::<f64>() {
+        // Also rejects NaN and infinities
+        if !(MIN_TIME_S as f64..MAX_TIME_S as f64).contains(&seconds) {
+            return Err(out_of_range());
+        }
+        return Ok((seconds * 1_000_000.0) as u64);
+    }
+
+    let date = DateTime::parse_from_rfc3339(time).map_err(|error| {
+        TimeError::Invalid(format!(
+            "Time should be a unix timestamp or RFC3339, {time}: {error}"
+        ))
+    })?;
+    if !(MIN_TIME_S..MAX_TIME_S).contains(&date.timestamp()) {
+        return Err(out_of_range());
+    }
+    Ok(date.timestamp_micros() as u64)
+}
+
+fn set_system_time(microseconds: u64) -> Result<(), TimeError> {
+    let mut timespec: libc::timespec = unsafe { std::mem::zeroed() };
+    timespec.tv_sec = (microseconds / 1_000_000) as libc::time_t;
+    timespec.tv_nsec = ((microseconds % 1_000_000) * 1_000) as _;
+    if unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &timespec) } != 0 {
+        return Err(TimeError::Io(format!(
+            "Failed to set system time: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+fn set_hwclock() -> Result<(), String> {
+    let output = Command::new("hwclock")
+        .arg("--systohc")
+        .output()
+        .map_err(|error| format!("Failed to run hwclock: {error}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "hwclock --systohc failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Boards without RTC boot with a clock far in the past, so moving forward is always accepted
+pub fn set_time(update: &TimeUpdate) -> Result<TimeJump, TimeError> {
+    let microseconds = parse_time_us(&update.time)?;
+    let previous_microseconds = now_us();
+    let jump_ms = (microseconds as i64 - previous_microseconds as i64) / 1_000;
+
+    let previous_time = journal::format_timestamp(previous_microseconds);
+    let time = journal::format_timestamp(microseconds);
+    if jump_ms < 0 && !update.force.unwrap_or(false) {
+        return Err(TimeError::Backwards(format!(
+            "Refusing to move time backwards from {previous_time} to {time}, use force to allow it"
+        )));
+    }
+
+    set_system_time(microseconds)?;
+    warn!("System time changed by {jump_ms} ms: {previous_time} -> {time}");
+
+    // The system time is already changed, so a hwclock failure is reported in the response
+    let mut is_hwclock_set = false;
+    let mut hwclock_error = None;
+    if update.set_hwclock.unwrap_or(false) {
+        match set_hwclock() {
+            Ok(()) => {
+                info!("Hardware clock set from system time");
+                is_hwclock_set = true;
+            }
+            Err(error) => {
+                warn!("{error}");
+                hwclock_error = Some(error);
+            }
+        }
+    }
+
+    Ok(TimeJump {
+        previous_time,
+        time,
+        jump_ms,
+        is_hwclock_set,
+        hwclock_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_bearer_token() {
+        assert!(is_authorized("secret", Some("Bearer secret")));
+        assert!(!is_authorized("secret", Some("Bearer secreT")));
+        assert!(!is_authorized("secret", Some("Bearer secret2")));
+        assert!(!is_authorized("secret", Some("secret")));
+        assert!(!is_authorized("secret", None));
+        assert!(!is_authorized("", Some("Bearer ")));
+    }
+
+    #[test]
+    fn parses_time_in_range() {
+        assert_eq!(
+            parse_time_us("1700000000.5").unwrap(),
+            1_700_000_000_500_000
+        );
+        assert_eq!(
+            parse_time_us("2023-11-14T22:13:20Z").unwrap(),
+            1_700_000_000_000_000
+        );
+        assert_eq!(
+            parse_time_us("2000-01-01T00:00:00Z").unwrap(),
+            946_684_800_000_000
+        );
+
+        for time in [
+            "1700000000000",
+            "-1",
+            "NaN",
+            "inf",
+            "1e300",
+            "1999-12-31T23:59:59Z",
+            "2100-01-01T00:00:00Z",
+            "+262143-01-01T00:00:00Z",
+            "yesterday",
+        ] {
+            assert!(
+                matches!(parse_time_us(time), Err(TimeError::Invalid(_))),
+                "{}",
+                time
+            );
+        }
+    }
+}
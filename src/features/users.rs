@@ -0,0 +1,186 @@
+use crate::features::journal;
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::convert::TryInto;
+use std::path::Path;
+use std::time::SystemTime;
+
+const UTMP_PATHS: [&str; 2] = ["/run/utmp", "/var/run/utmp"];
+const WTMP_PATH: &str = "/var/log/wtmp";
+const BTMP_PATH: &str = "/var/log/btmp";
+const RECENT_ENTRIES: usize = 50;
+
+// From utmp.h, records have the same layout on 32 and 64 bits glibc targets
+const UTMP_RECORD_SIZE: usize = 384;
+const LOGIN_PROCESS: i16 = 6;
+const USER_PROCESS: i16 = 7;
+const DEAD_PROCESS: i16 = 8;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Session {
+    user: String,
+    tty: String,
+    remote_host: Option<String>,
+    pid: i32,
+    login_time: String,
+    idle_s: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Login {
+    user: String,
+    tty: String,
+    remote_host: Option<String>,
+    time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logout_time: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct Users {
+    sessions: Vec<Session>,
+    recent_logins: Vec<Login>,
+    recent_failures: Vec<Login>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct UtmpRecord {
+    record_type: i16,
+    pid: i32,
+    line: String,
+    user: String,
+    host: String,
+    timestamp_us: u64,
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+fn i32_at(record: &[u8], offset: usize) -> i32 {
+    i32::from_ne_bytes(record[offset..offset + 4].try_into().unwrap())
+}
+
+// struct utmp: type (i16 + padding), pid (i32), line [32], id [4], user [32], host [256],
+// exit (2 x i16), session (i32), tv (2 x i32), addr_v6 [4 x i32] and 20 unused bytes
+fn parse_record(record: &[u8]) -> UtmpRecord {
+    UtmpRecord {
+        record_type: i16::from_ne_bytes([record[0], record[1]]),
+        pid: i32_at(record, 4),
+        line: c_string(&record[8..40]),
+        user: c_string(&record[44..76]),
+        host: c_string(&record[76..332]),
+        timestamp_us: i32_at(record, 340) as u32 as u64 * 1_000_000
+            + i32_at(record, 344) as u32 as u64,
+    }
+}
+
+fn read_records(path: &str) -> Result<Vec<UtmpRecord>, String> {
+    let content = std::fs::read(path).map_err(|error| format!("Failed to read {path}: {error}"))?;
+    Ok(content
+        .chunks_exact(UTMP_RECORD_SIZE)
+        .map(parse_record)
+        .collect())
+}
+
+impl Login {
+    fn from(record: &UtmpRecord) -> Self {
+        Login {
+            user: record.user.clone(),
+            tty: record.line.clone(),
+            remote_host: Some(record.host.clone()).filter(|host| !host.is_empty()),
+            time: journal::format_timestamp(record.timestamp_us),
+            logout_time: None,
+        }
+    }
+}
+
+// The terminal access time is updated on input, like who and w do
+fn idle_s(tty: &str) -> Option<u64> {
+    let accessed = std::fs::metadata(Path::new("/dev").join(tty))
+        .ok()?
+        .accessed()
+        .ok()?;
+    SystemTime::now()
+        .duration_since(accessed)
+        .ok()
+        .map(|idle| idle.as_secs())
+}
+
+fn sessions() -> Result<Vec<Session>, String> {
+    let path = UTMP_PATHS
+        .iter()
+        .find(|path| Path::new(path).exists())
+        .ok_or_else(|| format!("utmp not found in: {}", UTMP_PATHS.join(", ")))?;
+
+    Ok(read_records(path)?
+        .iter()
+        // Entries may be left behind by processes that died without cleaning them up
+        .filter(|record| record.record_type == USER_PROCESS)
+        .filter(|record| Path::new("/proc").join(record.pid.to_string()).exists())
+        .map(|record| Session {
+            user: record.user.clone(),
+            tty: record.line.clone(),
+            remote_host: Some(record.host.clone()).filter(|host| !host.is_empty()),
+            pid: record.pid,
+            login_time: journal::format_timestamp(record.timestamp_us),
+            idle_s: idle_s(&record.line),
+        })
+        .collect())
+}
+
+// Logins matched with the next logout of the same terminal, newest first, like last does
+fn recent_logins() -> Result<Vec<Login>, String> {
+    let records = read_records(WTMP_PATH)?;
+    Ok(records
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, record)| record.record_type == USER_PROCESS)
+        .take(RECENT_ENTRIES)
+        .map(|(index, record)| {
+            let mut login = Login::from(record);
+            login.logout_time = records[index + 1..]
+                .iter()
+                .find(|next| next.line == record.line && next.record_type != LOGIN_PROCESS)
+                .filter(|next| next.record_type == DEAD_PROCESS)
+                .map(|next| journal::format_timestamp(next.timestamp_us));
+            login
+        })
+        .collect())
+}
+
+// Failed attempts are logged to btmp, which is only readable by root
+fn recent_failures() -> Result<Vec<Login>, String> {
+    Ok(read_records(BTMP_PATH)?
+        .iter()
+        .rev()
+        .take(RECENT_ENTRIES)
+        .map(Login::from)
+        .collect())
+}
+
+fn or_error<T>(result: Result<Vec<T>, String>, errors: &mut Vec<String>) -> Vec<T> {
+    result.unwrap_or_else(|error| {
+        errors.push(error);
+        vec![]
+    })
+}
+
+#[cached(time = 5)]
+pub fn users() -> Users {
+    let mut errors = vec![];
+    Users {
+        sessions: or_error(sessions(), &mut errors),
+        recent_logins: or_error(recent_logins(), &mut errors),
+        recent_failures: or_error(recent_failures(), &mut errors),
+        errors,
+    }
+}
@@ -0,0 +1,315 @@
+use cached::proc_macro::cached;
+use paperclip::actix::Apiv2Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Command;
+use tracing::*;
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct WifiInterface {
+    name: String,
+    is_connected: bool,
+    ssid: Option<String>,
+    bssid: Option<String>,
+    frequency_MHz: Option<u32>,
+    channel: Option<u32>,
+    signal_dBm: Option<i32>,
+    noise_dBm: Option<i32>,
+    link_quality: Option<f32>,
+    rx_bitrate_Mbps: Option<f32>,
+    tx_bitrate_Mbps: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct WifiScanResult {
+    interface: String,
+    bssid: String,
+    ssid: Option<String>,
+    is_associated: bool,
+    frequency_MHz: Option<u32>,
+    channel: Option<u32>,
+    signal_dBm: Option<f32>,
+    security: String,
+    last_seen_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Apiv2Schema)]
+pub struct WifiScan {
+    networks: Vec<WifiScanResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct WirelessStatistics {
+    link_quality: Option<f32>,
+    level_dBm: Option<i32>,
+    noise_dBm: Option<i32>,
+}
+
+fn channel_from_frequency(frequency_MHz: u32) -> Option<u32> {
+    match frequency_MHz {
+        2484 => Some(14),
+        2412..=2472 => Some((frequency_MHz - 2407) / 5),
+        5150..=5895 => Some((frequency_MHz - 5000) / 5),
+        5955..=7115 => Some((frequency_MHz - 5950) / 5),
+        _ => None,
+    }
+}
+
+fn wireless_interfaces() -> Vec<String> {
+    let mut interfaces = std::fs::read_dir("/sys/class/net")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    entry.path().join("wireless").exists() || entry.path().join("phy80211").exists()
+                })
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    interfaces.sort();
+    interfaces
+}
+
+// Format:
+// Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+//  face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+//  wlan0: 0000   54.  -56.  -256        0      0      0      0      0        0
+fn wireless_statistics() -> HashMap<String, WirelessStatistics> {
+    let content = std::fs::read_to_string("/proc/net/wireless").unwrap_or_default();
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (interface, values) = line.split_once(':')?;
+            let values = values
+                .split_whitespace()
+                .map(|value| value.trim_end_matches('.'))
+                .collect::<Vec<&str>>();
+            // Drivers report -256 or 0 when the value is not available
+            let dBm = |index: usize| {
+                values
+                    .get(index)
+                    .and_then(|value| value.parse::<i32>().ok())
+                    .filter(|value| *value < 0 && *value > -256)
+            };
+            Some((
+                interface.trim().to_string(),
+                WirelessStatistics {
+                    link_quality: values.get(1).and_then(|value| value.parse().ok()),
+                    level_dBm: dBm(2),
+                    noise_dBm: dBm(3),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn iw(arguments: &[&str]) -> Result<String, String> {
+    let output = Command::new("iw")
+        .args(arguments)
+        .output()
+        .map_err(|error| format!("Failed to run iw: {error}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "iw {} failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn parse_bitrate(value: &str) -> Option<f32> {
+    // e.g: "390.0 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 1"
+    value.split_whitespace().next()?.parse().ok()
+}
+
+fn parse_frequency(value: &str) -> Option<u32> {
+    // Newer iw versions print decimals, e.g: "5180.0"
+    value
+        .split_whitespace()
+        .next()?
+        .parse::<f32>()
+        .ok()
+        .map(|frequency| frequency as u32)
+}
+
+impl WifiInterface {
+    fn from(name: &str, statistics: Option<&WirelessStatistics>) -> Self {
+        let statistics = statistics.cloned().unwrap_or_default();
+        let mut interface = WifiInterface {
+            name: name.into(),
+            is_connected: false,
+            ssid: None,
+            bssid: None,
+            frequency_MHz: None,
+            channel: None,
+            signal_dBm: statistics.level_dBm,
+            noise_dBm: statistics.noise_dBm,
+            link_quality: statistics.link_quality,
+            rx_bitrate_Mbps: None,
+            tx_bitrate_Mbps: None,
+            error: None,
+        };
+
+        // Connected to aa:bb:cc:dd:ee:ff (on wlan0)
+        //     SSID: network
+        //     freq: 5180
+        //     signal: -56 dBm
+        //     rx bitrate: 390.0 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 1
+        //     tx bitrate: 433.3 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 1
+        let output = match iw(&["dev", name, "link"]) {
+            Ok(output) => output,
+            Err(error) => {
+                debug!("{error}");
+                interface.error = Some(error);
+                return interface;
+            }
+        };
+
+        for line in output.lines() {
+            let line = line.trim();
+            if let Some(bssid) = line.strip_prefix("Connected to ") {
+                interface.is_connected = true;
+                interface.bssid = bssid.split_whitespace().next().map(String::from);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "SSID" => interface.ssid = Some(value.into()),
+                "freq" => {
+                    interface.frequency_MHz = parse_frequency(value);
+                    interface.channel = interface.frequency_MHz.and_then(channel_from_frequency);
+                }
+                "signal" => {
+                    interface.signal_dBm = value
+                        .split_whitespace()
+                        .next()
+                        .and_then(|signal| signal.parse().ok())
+                        .or(interface.signal_dBm)
+                }
+                "rx bitrate" => interface.rx_bitrate_Mbps = parse_bitrate(value),
+                "tx bitrate" => interface.tx_bitrate_Mbps = parse_bitrate(value),
+                _ => (),
+            }
+        }
+
+        interface
+    }
+}
+
+#[cached(time = 5)]
+pub fn wifi() -> Vec<WifiInterface> {
+    let statistics = wireless_statistics();
+    wireless_interfaces()
+        .iter()
+        .map(|name| WifiInterface::from(name, statistics.get(name)))
+        .collect()
+}
+
+fn parse_scan(interface: &str, output: &str) -> Vec<WifiScanResult> {
+    let mut results: Vec<WifiScanResult> = vec![];
+    // Each access point starts with: BSS aa:bb:cc:dd:ee:ff(on wlan0) -- associated
+    for line in output.lines() {
+        if let Some(bss) = line.strip_prefix("BSS ") {
+            let bssid = bss
+                .split(|c: char| c == '(' || c.is_whitespace())
+                .next()
+                .unwrap_or_default();
+            results.push(WifiScanResult {
+                interface: interface.into(),
+                bssid: bssid.into(),
+                ssid: None,
+                is_associated: bss.contains("-- associated"),
+                frequency_MHz: None,
+                channel: None,
+                signal_dBm: None,
+                security: "open".into(),
+                last_seen_ms: None,
+            });
+            continue;
+        }
+
+        let Some(result) = results.last_mut() else {
+            continue;
+        };
+
+        let line = line.trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "SSID" if !value.is_empty() => result.ssid = Some(value.into()),
+            "freq" => {
+                result.frequency_MHz = parse_frequency(value);
+                result.channel = result.frequency_MHz.and_then(channel_from_frequency);
+            }
+            "signal" => {
+                result.signal_dBm = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|signal| signal.parse().ok())
+            }
+            "last seen" => {
+                result.last_seen_ms = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|time| time.parse().ok())
+            }
+            "capability" if value.contains("Privacy") && result.security == "open" => {
+                result.security = "wep".into()
+            }
+            "WPA" if result.security != "wpa2" => result.security = "wpa".into(),
+            "RSN" => result.security = "wpa2".into(),
+            _ => (),
+        }
+    }
+
+    results
+}
+
+// Triggers a scan on the desired interface, or all wireless interfaces, scanning requires root
+// Scans take a few seconds per interface and block the calling thread
+pub fn scan(interface: Option<String>) -> Result<WifiScan, String> {
+    let interfaces = wireless_interfaces();
+    let interfaces = match interface {
+        Some(interface) if !interfaces.contains(&interface) => {
+            return Err(format!("Wireless interface not found: {interface}"));
+        }
+        Some(interface) => vec![interface],
+        None => interfaces,
+    };
+
+    // A failing interface, e.g: down or busy, should not hide the results of the others
+    let mut results = vec![];
+    let mut errors = vec![];
+    for interface in interfaces {
+        match iw(&["dev", &interface, "scan"]) {
+            Ok(output) => results.extend(parse_scan(&interface, &output)),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    results.sort_by(|a, b| {
+        b.signal_dBm
+            .unwrap_or(f32::MIN)
+            .partial_cmp(&a.signal_dBm.unwrap_or(f32::MIN))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(WifiScan {
+        networks: results,
+        errors,
+    })
+}
@@ -20,6 +20,11 @@ async fn main() {
     }
 
     features::platform::start();
+    features::system::start();
+    features::block_devices::start();
+    features::mounts::start();
+    features::power::start();
+    features::serial_hotplug::start();
     recorder::start();
     server::run(&format!("0.0.0.0:{}", cli::args().as_ref().port));
 }
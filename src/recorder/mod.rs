@@ -20,6 +20,7 @@ pub fn start() {
 
     let mut kernel_client = features::kernel::ask_for_client();
     let mut journal_client = features::journal::ask_for_client();
+    let mut serial_hotplug_client = features::serial_hotplug::ask_for_new_events_client();
 
     _spawn(module_path!().into(), async move {
         let mut counter: u64 = 0;
@@ -51,6 +52,15 @@ pub fn start() {
                     .unwrap();
             }
 
+            while let Ok(Some(message)) = serial_hotplug_client.try_next() {
+                info!("Sending serial hotplug event to zenoh: {message}");
+                zenoh_session
+                    .put(zenoh_topic_name.replace("{}", "serial_hotplug"), message)
+                    .encoding(zenoh::bytes::Encoding::APPLICATION_JSON)
+                    .await
+                    .unwrap();
+            }
+
             for (category, interval) in categories.iter() {
                 if counter % interval != 0 {
                     continue;
@@ -59,15 +69,39 @@ pub fn start() {
                 let topic_name =
                     zenoh_topic_name.replace("{}", &category.to_string().replace("-", "_"));
                 let data = match category {
+                    cli::LogSetting::BlockDevices => match features::block_devices::block_devices()
+                    {
+                        Ok(devices) => serde_json::to_string(&devices).unwrap(),
+                        Err(error) => {
+                            warn!("Failed to get {category}: {error}");
+                            continue;
+                        }
+                    },
                     cli::LogSetting::Netstat => {
-                        serde_json::to_string(&features::netstat::netstat()).unwrap()
+                        match features::network::netstat::netstat(None, None, None, None, None) {
+                            Ok(netstat) => serde_json::to_string(&netstat).unwrap(),
+                            Err(error) => {
+                                warn!("Failed to get {category}: {error}");
+                                continue;
+                            }
+                        }
                     }
                     cli::LogSetting::Platform => {
                         serde_json::to_string(&features::platform::platform()).unwrap()
                     }
+                    cli::LogSetting::Power => {
+                        serde_json::to_string(&features::power::power()).unwrap()
+                    }
                     cli::LogSetting::SerialPorts => {
                         serde_json::to_string(&features::serial::serial(None)).unwrap()
                     }
+                    cli::LogSetting::SystemdFailed => match features::systemd::failed_summary() {
+                        Ok(summary) => serde_json::to_string(&summary).unwrap(),
+                        Err(error) => {
+                            warn!("Failed to get {category}: {error}");
+                            continue;
+                        }
+                    },
                     cli::LogSetting::Cpu => {
                         serde_json::to_string(&features::system::cpu()).unwrap()
                     }
@@ -78,7 +112,7 @@ pub fn start() {
                         serde_json::to_string(&features::system::info()).unwrap()
                     }
                     cli::LogSetting::Memory => {
-                        serde_json::to_string(&features::system::memory()).unwrap()
+                        serde_json::to_string(&features::system::memory(None)).unwrap()
                     }
                     cli::LogSetting::Network => {
                         serde_json::to_string(&features::system::network()).unwrap()
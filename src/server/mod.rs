@@ -21,19 +21,54 @@ pub fn run(server_address: &str) {
                 r"/{filename:.*(\.html|\.js|\.css)}",
                 web::get().to(pages::root),
             )
+            .route("/containers", web::get().to(pages::containers))
+            .route("/health", web::get().to(pages::health))
+            .route("/kernel/cmdline", web::get().to(pages::kernel_cmdline))
+            .route("/kernel/modules", web::get().to(pages::kernel_modules))
+            .route("/kernel/sysctl", web::get().to(pages::kernel_sysctl))
             .route("/kernel_buffer", web::get().to(pages::kernel_buffer))
             .route("/journal", web::get().to(pages::journal))
             .route("/model", web::get().to(pages::model))
             .route("/netstat", web::get().to(pages::netstat))
+            .route(
+                "/netstat/listening",
+                web::get().to(pages::netstat_listening),
+            )
+            .route("/network/dns", web::get().to(pages::network_dns))
+            .route(
+                "/network/neighbors",
+                web::get().to(pages::network_neighbors),
+            )
+            .route(
+                "/network/process_traffic",
+                web::get().to(pages::network_process_traffic),
+            )
+            .route("/network/routes", web::get().to(pages::network_routes))
             .route("/platform", web::get().to(pages::platform))
             .route("/serial", web::get().to(pages::serial))
+            .route("/serial/sessions", web::get().to(pages::serial_sessions))
             .route("/system", web::get().to(pages::system))
+            .route("/system/cgroups", web::get().to(pages::system_cgroups))
             .route("/system/cpu", web::get().to(pages::system_cpu))
+            .route("/system/cpu_times", web::get().to(pages::system_cpu_times))
+            .route("/system/cpufreq", web::get().to(pages::system_cpufreq))
+            .route(
+                "/system/cpufreq/{policy}",
+                web::put().to(pages::system_cpufreq_update),
+            )
             .route("/system/disk", web::get().to(pages::system_disk))
+            .route(
+                "/system/block_devices",
+                web::get().to(pages::system_block_devices),
+            )
             .route("/system/info", web::get().to(pages::system_info))
             .route("/system/memory", web::get().to(pages::system_memory))
+            .route("/system/mounts", web::get().to(pages::system_mounts))
             .route("/system/network", web::get().to(pages::system_network))
+            .route("/system/power", web::get().to(pages::system_power))
             .route("/system/process", web::get().to(pages::system_process))
+            .route("/system/sensors", web::get().to(pages::system_sensors))
+            .route("/system/thermal", web::get().to(pages::system_thermal))
             .route(
                 "/system/temperature",
                 web::get().to(pages::system_temperature),
@@ -42,12 +77,38 @@ pub fn run(server_address: &str) {
                 "/system/unix_time_seconds",
                 web::get().to(pages::system_unix_time_seconds),
             )
+            .route("/system/time", web::get().to(pages::system_time))
+            .route("/system/time", web::put().to(pages::system_time_update))
+            .route("/system/users", web::get().to(pages::system_users))
+            .route("/system/wifi", web::get().to(pages::system_wifi))
+            .route("/system/wifi/scan", web::get().to(pages::system_wifi_scan))
+            .route("/systemd/failed", web::get().to(pages::systemd_failed))
+            .route("/systemd/timers", web::get().to(pages::systemd_timers))
+            .route("/systemd/units", web::get().to(pages::systemd_units))
+            .route("/systemd/units/{name}", web::get().to(pages::systemd_unit))
+            .route(
+                "/systemd/units/{name}/{action}",
+                web::post().to(pages::systemd_unit_control),
+            )
             .route("/udev", web::get().to(pages::udev))
             .route(
                 "/ws/kernel_buffer",
                 web::get().to(pages::websocket_kernel_buffer),
             )
             .route("/ws/journal", web::get().to(pages::websocket_journal))
+            .route(
+                "/ws/containers/{id}/logs",
+                web::get().to(pages::websocket_container_logs),
+            )
+            .route(
+                "/ws/serial_hotplug",
+                web::get().to(pages::websocket_serial_hotplug),
+            )
+            // Ports are relative to /dev and may contain slashes, e.g: pts/3 or serial/by-id/..
+            .route(
+                "/ws/serial/{port:.*}",
+                web::get().to(pages::websocket_serial),
+            )
             .build()
     })
     .bind(server_address)
@@ -50,6 +50,14 @@ pub struct JournalQuery {
     size: Option<usize>,
 }
 
+#[api_v2_operation]
+/// Provides service health, like clock synchronization state
+pub fn health(req: HttpRequest) -> Json<features::health::Health> {
+    debug!("{:#?}", req);
+
+    Json(features::health::health())
+}
+
 #[api_v2_operation]
 /// Provides kernel information, like dmesg
 pub fn kernel_buffer(
@@ -63,6 +71,57 @@ pub fn kernel_buffer(
     Json(features::kernel::messages(query.start, query.size))
 }
 
+#[api_v2_operation]
+/// Provides loaded kernel modules, like lsmod
+pub fn kernel_modules(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::kernel_info::modules() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[api_v2_operation]
+/// Provides the kernel command line and its parameters
+pub fn kernel_cmdline(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::kernel_info::cmdline() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct SysctlQuery {
+    prefix: Option<String>,
+}
+
+#[api_v2_operation]
+/// Provides kernel parameters from /proc/sys, like sysctl -a, optionally filtered by a prefix (e.g: net.ipv4)
+pub fn kernel_sysctl(req: HttpRequest, query: web::Query<SysctlQuery>) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let query = query.into_inner();
+    match features::kernel_info::sysctl(query.prefix) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
 #[api_v2_operation]
 /// Provides systemd journal output similar to journalctl -o json
 pub fn journal(
@@ -75,12 +134,117 @@ pub fn journal(
     Json(features::journal::entries(query.start, query.size))
 }
 
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct NetstatQuery {
+    state: Option<String>,
+    port: Option<u16>,
+    pid: Option<u32>,
+    unix: Option<bool>,
+    details: Option<bool>,
+}
+
+#[api_v2_operation]
+/// Provides the same output as netstat: TCP/UDP/Unix sockets that are in use and who is using it
+/// Use details=true for TCP queues, RTT, retransmits and congestion window
+/// UDP sockets have no state and are only provided with state=LISTEN or without state filter
+pub fn netstat(req: HttpRequest, query: web::Query<NetstatQuery>) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let query = query.into_inner();
+
+    match features::network::netstat::netstat(
+        query.state,
+        query.port,
+        query.pid,
+        query.unix,
+        query.details,
+    ) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
 #[api_v2_operation]
-/// Provides the same output as netstat: TCP/UDP ports that are in use and who is using it
-pub fn netstat(req: HttpRequest) -> Json<features::netstat::Netstat> {
+/// Provides a summary of listening TCP and UDP ports and the processes behind them
+pub fn netstat_listening(req: HttpRequest) -> HttpResponse {
     debug!("{:#?}", req);
 
-    Json(features::netstat::netstat())
+    match features::network::netstat::listening() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[api_v2_operation]
+/// Provides TCP traffic per process over the last sampling interval, busiest processes first, sampling starts with the first request
+pub fn network_process_traffic(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::network::process_traffic::process_traffic() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[api_v2_operation]
+/// Provides the IPv4 and IPv6 routing tables
+pub fn network_routes(req: HttpRequest) -> Json<Vec<features::network::routes::Route>> {
+    debug!("{:#?}", req);
+
+    Json(features::network::routes::routes())
+}
+
+#[api_v2_operation]
+/// Provides the ARP and NDP neighbor tables with their states
+pub fn network_neighbors(req: HttpRequest) -> Json<Vec<features::network::neighbors::Neighbor>> {
+    debug!("{:#?}", req);
+
+    Json(features::network::neighbors::neighbors())
+}
+
+#[api_v2_operation]
+/// Provides name resolution configuration from resolv.conf and systemd-resolved
+pub fn network_dns(req: HttpRequest) -> Json<features::network::dns::Dns> {
+    debug!("{:#?}", req);
+
+    Json(features::network::dns::dns())
+}
+
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct ContainersQuery {
+    stats: Option<bool>,
+}
+
+#[api_v2_operation]
+/// Provides Docker or Podman containers, CPU, memory and network stats are optional since they take a second
+pub async fn containers(req: HttpRequest, query: web::Query<ContainersQuery>) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let query = query.into_inner();
+    // Stats take seconds per container, run the requests outside of the server workers
+    match web::block(move || features::containers::containers(query.stats)).await {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Error(error)) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: containers request was canceled"),
+    }
 }
 
 #[derive(Debug, Deserialize, Apiv2Schema)]
@@ -101,6 +265,14 @@ pub async fn serial(
     Json(features::serial::serial(query.udev))
 }
 
+#[api_v2_operation]
+/// Provides the active serial port websocket sessions
+pub async fn serial_sessions(req: HttpRequest) -> Json<Vec<features::serial::SerialSession>> {
+    debug!("{:#?}", req);
+
+    Json(features::serial::sessions())
+}
+
 #[api_v2_operation]
 /// Provides system information: cpu, disk, operating system, memory, network, processes, sensors
 pub async fn system(req: HttpRequest) -> Json<features::system::System> {
@@ -117,6 +289,76 @@ pub async fn system_cpu(req: HttpRequest) -> Json<Vec<features::system::Cpu>> {
     Json(features::system::cpu())
 }
 
+#[api_v2_operation]
+/// Provides per core and aggregated cpu time percentages, context switches and interrupts per second
+pub async fn system_cpu_times(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::system::cpu_times() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[api_v2_operation]
+/// Provides cpufreq policies: frequency limits, governors and time spent in each frequency
+pub async fn system_cpufreq(req: HttpRequest) -> Json<Vec<features::cpufreq::CpuFreqPolicy>> {
+    debug!("{:#?}", req);
+
+    Json(features::cpufreq::policies())
+}
+
+#[api_v2_operation]
+/// Changes the governor or frequency limits of a cpufreq policy, requires --enable-cpufreq-control
+pub async fn system_cpufreq_update(
+    req: HttpRequest,
+    policy: web::Path<String>,
+    update: web::Json<features::cpufreq::CpuFreqPolicyUpdate>,
+) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &update);
+
+    if !crate::cli::args().enable_cpufreq_control {
+        return HttpResponse::Forbidden()
+            .content_type("text/plain")
+            .body("error: cpufreq control is disabled, start with --enable-cpufreq-control");
+    }
+
+    match features::cpufreq::update_policy(&policy.into_inner(), &update.into_inner()) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => {
+            let mut response = match error {
+                features::cpufreq::CpuFreqError::NotFound(_) => HttpResponse::NotFound(),
+                features::cpufreq::CpuFreqError::Invalid(_) => HttpResponse::BadRequest(),
+                features::cpufreq::CpuFreqError::Io(_) => HttpResponse::InternalServerError(),
+            };
+            response
+                .content_type("text/plain")
+                .body(format!("error: {}", error))
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Provides cgroup v2 resources accounting as a tree: cpu, memory, io and pids for each slice, service and container
+pub async fn system_cgroups(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::cgroups::cgroups() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
 #[api_v2_operation]
 /// Provides system information for disk only
 pub async fn system_disk(req: HttpRequest) -> Json<Vec<features::system::Disk>> {
@@ -125,6 +367,36 @@ pub async fn system_disk(req: HttpRequest) -> Json<Vec<features::system::Disk>>
     Json(features::system::disk())
 }
 
+#[api_v2_operation]
+/// Provides block devices information and I/O statistics: IOPS, throughput, await times and utilization
+pub async fn system_block_devices(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::block_devices::block_devices() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
+#[api_v2_operation]
+/// Provides mounted filesystems with mount options, inode usage and read-only state
+pub async fn system_mounts(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    match features::mounts::mounts() {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+    }
+}
+
 #[api_v2_operation]
 /// Provides system information from operating system only
 pub async fn system_info(req: HttpRequest) -> Json<features::system::OsInfo> {
@@ -133,12 +405,22 @@ pub async fn system_info(req: HttpRequest) -> Json<features::system::OsInfo> {
     Json(features::system::info())
 }
 
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct MemoryQuery {
+    detailed: Option<bool>,
+}
+
 #[api_v2_operation]
-/// Provides system information for memory only
-pub async fn system_memory(req: HttpRequest) -> Json<features::system::Memory> {
-    debug!("{:#?}", req);
+/// Provides system information for memory only, use detailed=true for the /proc/meminfo and zram breakdown
+pub async fn system_memory(
+    req: HttpRequest,
+    query: web::Query<MemoryQuery>,
+) -> Json<features::system::Memory> {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let query = query.into_inner();
 
-    Json(features::system::memory())
+    Json(features::system::memory(query.detailed))
 }
 
 #[api_v2_operation]
@@ -157,6 +439,30 @@ pub async fn system_process(req: HttpRequest) -> Json<Vec<features::system::Proc
     Json(features::system::process())
 }
 
+#[api_v2_operation]
+/// Provides power supplies and batteries status, with time to empty estimates while discharging
+pub async fn system_power(req: HttpRequest) -> Json<Vec<features::power::PowerSupply>> {
+    debug!("{:#?}", req);
+
+    Json(features::power::power())
+}
+
+#[api_v2_operation]
+/// Provides hwmon sensor channels: voltages, currents, power, fans and temperatures
+pub async fn system_sensors(req: HttpRequest) -> Json<Vec<features::sensors::HwmonDevice>> {
+    debug!("{:#?}", req);
+
+    Json(features::sensors::sensors())
+}
+
+#[api_v2_operation]
+/// Provides thermal zones with their trip points and the cooling devices state
+pub async fn system_thermal(req: HttpRequest) -> Json<features::thermal::Thermal> {
+    debug!("{:#?}", req);
+
+    Json(features::thermal::thermal())
+}
+
 #[api_v2_operation]
 /// Provides system information for sensors only
 pub async fn system_temperature(req: HttpRequest) -> Json<Vec<features::system::Temperature>> {
@@ -165,6 +471,105 @@ pub async fn system_temperature(req: HttpRequest) -> Json<Vec<features::system::
     Json(features::system::temperature())
 }
 
+#[api_v2_operation]
+/// Provides system time, timezone, RTC time and clock synchronization state
+pub async fn system_time(req: HttpRequest) -> Json<features::time::Time> {
+    debug!("{:#?}", req);
+
+    Json(features::time::time())
+}
+
+#[api_v2_operation]
+/// Sets the system time from a unix timestamp or RFC3339 value, requires --time-control-token-file
+pub async fn system_time_update(
+    req: HttpRequest,
+    update: web::Json<features::time::TimeUpdate>,
+) -> HttpResponse {
+    // The request is not logged since its headers contain the authorization token
+    debug!("{:#?}", &update);
+
+    let Some(token_file) = crate::cli::args().time_control_token_file.clone() else {
+        return HttpResponse::Forbidden()
+            .content_type("text/plain")
+            .body("error: time control is disabled, start with --time-control-token-file");
+    };
+    let token = match std::fs::read_to_string(&token_file) {
+        Ok(token) => token.trim().to_string(),
+        Err(error) => {
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body(format!("error: Failed to read {token_file}: {error}"))
+        }
+    };
+    let authorization = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok());
+    if !features::time::is_authorized(&token, authorization) {
+        return HttpResponse::Unauthorized()
+            .content_type("text/plain")
+            .body("error: missing or invalid authorization token");
+    }
+
+    match features::time::set_time(&update.into_inner()) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(error) => {
+            let mut response = match error {
+                features::time::TimeError::Invalid(_) => HttpResponse::BadRequest(),
+                features::time::TimeError::Backwards(_) => HttpResponse::Conflict(),
+                features::time::TimeError::Io(_) => HttpResponse::InternalServerError(),
+            };
+            response
+                .content_type("text/plain")
+                .body(format!("error: {}", error))
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Provides logged in users sessions and recent logins and failures from utmp, wtmp and btmp
+pub async fn system_users(req: HttpRequest) -> Json<features::users::Users> {
+    debug!("{:#?}", req);
+
+    Json(features::users::users())
+}
+
+#[api_v2_operation]
+/// Provides wireless interfaces information: SSID, BSSID, channel, signal and bitrates
+pub async fn system_wifi(req: HttpRequest) -> Json<Vec<features::wifi::WifiInterface>> {
+    debug!("{:#?}", req);
+
+    Json(features::wifi::wifi())
+}
+
+#[derive(Debug, Deserialize, Apiv2Schema)]
+pub struct WifiScanQuery {
+    interface: Option<String>,
+}
+
+#[api_v2_operation]
+/// Scans for wireless networks on the desired interface, or all wireless interfaces
+pub async fn system_wifi_scan(req: HttpRequest, query: web::Query<WifiScanQuery>) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let query = query.into_inner();
+
+    // Scanning takes seconds, run it outside of the server workers
+    match web::block(move || features::wifi::scan(query.interface)).await {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Error(error)) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: Wi-Fi scan was canceled"),
+    }
+}
+
 #[api_v2_operation]
 /// Provides system information about current unix time
 pub async fn system_unix_time_seconds(req: HttpRequest) -> HttpResponse {
@@ -175,6 +580,119 @@ pub async fn system_unix_time_seconds(req: HttpRequest) -> HttpResponse {
         .body(features::system::unix_time_seconds().to_string())
 }
 
+#[api_v2_operation]
+/// Provides systemd units with their state and resources accounting
+pub async fn systemd_units(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    // systemctl can be slow to answer, run it outside of the server workers
+    match web::block(features::systemd::units).await {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Error(error)) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: systemd units request was canceled"),
+    }
+}
+
+#[api_v2_operation]
+/// Provides failed systemd units with their last journal lines
+pub async fn systemd_failed(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    // systemctl can be slow to answer, run it outside of the server workers
+    match web::block(features::systemd::failed).await {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Error(error)) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: systemd failed units request was canceled"),
+    }
+}
+
+#[api_v2_operation]
+/// Provides systemd timers with their last and next trigger times and last result
+pub async fn systemd_timers(req: HttpRequest) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    // systemctl can be slow to answer, run it outside of the server workers
+    match web::block(features::systemd::timers).await {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Error(error)) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body(format!("error: {}", error)),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: systemd timers request was canceled"),
+    }
+}
+
+fn systemd_response(
+    result: Result<
+        features::systemd::UnitDetails,
+        actix_web::error::BlockingError<features::systemd::SystemdError>,
+    >,
+) -> HttpResponse {
+    match result {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::to_string_pretty(&content).unwrap()),
+        Err(actix_web::error::BlockingError::Canceled) => HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("error: systemctl request was canceled"),
+        Err(actix_web::error::BlockingError::Error(error)) => {
+            let mut response = match error {
+                features::systemd::SystemdError::NotFound(_) => HttpResponse::NotFound(),
+                features::systemd::SystemdError::Io(_) => HttpResponse::InternalServerError(),
+            };
+            response
+                .content_type("text/plain")
+                .body(format!("error: {}", error))
+        }
+    }
+}
+
+#[api_v2_operation]
+/// Provides a systemd unit state and all of its properties
+pub async fn systemd_unit(req: HttpRequest, name: web::Path<String>) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    let name = name.into_inner();
+    systemd_response(web::block(move || features::systemd::unit(&name)).await)
+}
+
+#[api_v2_operation]
+/// Starts, stops or restarts a systemd unit, requires the unit in --systemd-control-units
+pub async fn systemd_unit_control(
+    req: HttpRequest,
+    path: web::Path<(String, features::systemd::UnitAction)>,
+) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &path);
+
+    let (name, action) = path.into_inner();
+    let name = features::systemd::unit_name(&name);
+    if !features::systemd::is_control_allowed(&name, &crate::cli::args().systemd_control_units) {
+        return HttpResponse::Forbidden()
+            .content_type("text/plain")
+            .body(format!(
+                "error: control of {name} is disabled, add it to --systemd-control-units"
+            ));
+    }
+
+    // Jobs wait for the unit to start or stop, up to its timeout, keep the server workers free
+    systemd_response(web::block(move || features::systemd::control(&name, &action)).await)
+}
+
 #[api_v2_operation]
 /// (WIP) Provides information about all devices connected to the main computer
 pub fn udev(req: HttpRequest) -> HttpResponse {
@@ -236,3 +754,68 @@ pub fn websocket_journal(req: HttpRequest, stream: web::Payload) -> HttpResponse
             .body(format!("error: {:#?}", error))
     })
 }
+
+pub fn websocket_container_logs(
+    req: HttpRequest,
+    id: web::Path<String>,
+    stream: web::Payload,
+) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    ws::start(
+        features::containers_websocket::new_websocket(id.into_inner()),
+        &req,
+        stream,
+    )
+    .unwrap_or_else(|error| {
+        HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body(format!("error: {:#?}", error))
+    })
+}
+
+pub fn websocket_serial_hotplug(req: HttpRequest, stream: web::Payload) -> HttpResponse {
+    debug!("{:#?}", req);
+
+    ws::start(
+        features::serial_hotplug_websocket::new_websocket(),
+        &req,
+        stream,
+    )
+    .unwrap_or_else(|error| {
+        HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body(format!("error: {:#?}", error))
+    })
+}
+
+pub fn websocket_serial(
+    req: HttpRequest,
+    port: web::Path<String>,
+    query: web::Query<features::serial::SerialSettings>,
+    stream: web::Payload,
+) -> HttpResponse {
+    debug!("{:#?}, {:#?}", req, &query);
+
+    let client = req.peer_addr().map(|address| address.to_string());
+    let websocket = match features::serial_websocket::new_websocket(&port, &query, client) {
+        Ok(websocket) => websocket,
+        Err(error) => {
+            let mut response = match error {
+                features::serial::SerialError::NotFound(_) => HttpResponse::NotFound(),
+                features::serial::SerialError::Invalid(_) => HttpResponse::BadRequest(),
+                features::serial::SerialError::Busy(_) => HttpResponse::Conflict(),
+                features::serial::SerialError::Io(_) => HttpResponse::InternalServerError(),
+            };
+            return response
+                .content_type("text/plain")
+                .body(format!("error: {}", error));
+        }
+    };
+
+    ws::start(websocket, &req, stream).unwrap_or_else(|error| {
+        HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body(format!("error: {:#?}", error))
+    })
+}